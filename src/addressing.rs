@@ -0,0 +1,68 @@
+//! Row selection of different panel types
+//!
+//! Most panels select the row with the binary address on the a to e pins, but
+//! some use a shift register clocked through the address pins instead.
+//! Other schemes can be supported by implementing `Addressing`.
+
+/// The address pins
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    A,
+    B,
+    C,
+    D,
+    E,
+}
+
+/// Drives the address pins to select a row
+pub trait Addressing {
+    /// Select `row` out of `rows` by setting the address pins with `set`
+    fn select(&self, row: usize, rows: usize, set: &mut dyn FnMut(Signal, bool));
+}
+
+/// The row is the binary number on the a to e pins, used by most panels
+pub struct Direct;
+
+impl Addressing for Direct {
+    fn select(&self, row: usize, _rows: usize, set: &mut dyn FnMut(Signal, bool)) {
+        set(Signal::A, row & 1 != 0);
+        set(Signal::B, row & 2 != 0);
+        set(Signal::C, row & 4 != 0);
+        set(Signal::D, row & 8 != 0);
+        set(Signal::E, row & 16 != 0);
+    }
+}
+
+/// The rows are selected by a shift register, e.g. a 74HC164
+///
+/// a is the clock and b the data input. A low bit is shifted in for the selected
+/// row and high bits for all others.
+pub struct ShiftRegister;
+
+impl Addressing for ShiftRegister {
+    fn select(&self, row: usize, rows: usize, set: &mut dyn FnMut(Signal, bool)) {
+        for i in 0..rows {
+            set(Signal::A, false);
+            set(Signal::B, i != rows - 1 - row);
+            set(Signal::A, true);
+        }
+    }
+}
+
+/// Rows scanned in a different order, as on some P6 panels
+///
+/// Selecting row `n` selects `order[n]` with `addressing` instead. Rows
+/// missing from `order` are selected unchanged.
+pub struct Remap {
+    pub addressing: &'static (dyn Addressing + Sync),
+    /// The address for every row, e.g. `&[0, 4, 8, 12, 1, 5, 9, 13, ...]`
+    pub order: &'static [usize],
+}
+
+impl Addressing for Remap {
+    fn select(&self, row: usize, rows: usize, set: &mut dyn FnMut(Signal, bool)) {
+        // Indexing could panic in the output loop, e.g. in an interrupt handler
+        let row = self.order.get(row).copied().unwrap_or(row);
+        self.addressing.select(row, rows, set);
+    }
+}
@@ -0,0 +1,205 @@
+//! Playing animations stored in flash
+//!
+//! Animations use a simple run length encoded format, which can be created
+//! from gifs or videos with a small script on the pc:
+//!
+//! - `H75A`
+//! - width, height and number of frames, each as a little endian `u16`
+//! - for every frame, the time to show it in ms as a little endian `u16`,
+//!   followed by runs of pixels until the frame is filled, row by row
+//! - every run is the number of pixels (1-255) and their red, green and blue value
+//!
+//! ```ignore
+//! static DATA: &[u8] = include_bytes!("animation.h75a");
+//! let mut player = Player::new(Animation::new(DATA).unwrap());
+//! loop {
+//!     if player.update(millis()) {
+//!         player.draw(&mut display).unwrap();
+//!     }
+//!     display.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+
+const MAGIC: &[u8] = b"H75A";
+const HEADER_LEN: usize = MAGIC.len() + 6;
+
+/// Problems found in the animation data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The data doesn't start with `H75A` or has no frames
+    InvalidHeader,
+    /// The data ends within a frame
+    Truncated,
+    /// A run is empty or extends past the end of its frame
+    InvalidRun,
+}
+
+/// A validated animation
+#[derive(Clone, Copy, Debug)]
+pub struct Animation<'a> {
+    data: &'a [u8],
+    size: Size,
+    frames: u16,
+}
+
+impl<'a> Animation<'a> {
+    /// Check the animation data
+    ///
+    /// Data after the last frame is ignored, e.g. padding added by the tools
+    /// storing it in flash.
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+            return Err(Error::InvalidHeader);
+        }
+        let value = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let animation = Self {
+            data,
+            size: Size::new(value(4) as u32, value(6) as u32),
+            frames: value(8),
+        };
+        if animation.frames == 0 {
+            return Err(Error::InvalidHeader);
+        }
+        let mut offset = HEADER_LEN;
+        for _ in 0..animation.frames {
+            offset = animation.frame_end(offset)?;
+        }
+        Ok(animation)
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Number of frames
+    pub fn frames(&self) -> u16 {
+        self.frames
+    }
+
+    /// Find the end of the frame starting at `offset`
+    fn frame_end(&self, mut offset: usize) -> Result<usize, Error> {
+        let pixels = self.size.width * self.size.height;
+        offset += 2;
+        let mut filled = 0;
+        while filled < pixels {
+            let run = self.data.get(offset..offset + 4).ok_or(Error::Truncated)?;
+            let count = run[0] as u32;
+            if count == 0 || filled + count > pixels {
+                return Err(Error::InvalidRun);
+            }
+            filled += count;
+            offset += 4;
+        }
+        if offset > self.data.len() {
+            return Err(Error::Truncated);
+        }
+        Ok(offset)
+    }
+
+    /// Time to show the frame starting at `offset` in ms
+    fn delay(&self, offset: usize) -> u32 {
+        u16::from_le_bytes([self.data[offset], self.data[offset + 1]]) as u32
+    }
+}
+
+/// Plays an animation in a loop
+pub struct Player<'a> {
+    animation: Animation<'a>,
+    /// Start of the current frame in the data
+    offset: usize,
+    /// Index of the current frame
+    frame: u16,
+    /// Time the current frame was first shown
+    shown: Option<u32>,
+}
+
+impl<'a> Player<'a> {
+    /// Create a new player, starting at the first frame
+    pub fn new(animation: Animation<'a>) -> Self {
+        Self {
+            animation,
+            offset: HEADER_LEN,
+            frame: 0,
+            shown: None,
+        }
+    }
+
+    /// Start over at the first frame
+    pub fn reset(&mut self) {
+        self.offset = HEADER_LEN;
+        self.frame = 0;
+        self.shown = None;
+    }
+
+    /// Advance the animation to the time `now`, in ms
+    ///
+    /// The clock may wrap around. Returns `true` if another frame has to be drawn.
+    pub fn update(&mut self, now: u32) -> bool {
+        let shown = match self.shown {
+            Some(shown) => shown,
+            None => {
+                self.shown = Some(now);
+                return true;
+            }
+        };
+        let delay = self.animation.delay(self.offset);
+        if now.wrapping_sub(shown) < delay {
+            return false;
+        }
+        // Data after the last frame isn't part of the animation
+        self.frame += 1;
+        if self.frame == self.animation.frames {
+            self.frame = 0;
+            self.offset = HEADER_LEN;
+        } else {
+            // Validated in `Animation::new`
+            self.offset = self.animation.frame_end(self.offset).unwrap_or(HEADER_LEN);
+        }
+        // Keep the timing when frames are drawn late
+        self.shown = Some(shown.wrapping_add(delay));
+        true
+    }
+
+    /// Draw the current frame to the top left corner of `target`
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+        D::Color: From<Rgb888>,
+    {
+        let pixels = self.animation.size.width * self.animation.size.height;
+        let runs = self.animation.data[self.offset + 2..].chunks_exact(4);
+        let colors = runs
+            .flat_map(|run| {
+                let color = Rgb888::new(run[1], run[2], run[3]).into();
+                core::iter::repeat_n(color, run[0] as usize)
+            })
+            .take(pixels as usize);
+        target.fill_contiguous(&Rectangle::new(Point::zero(), self.animation.size), colors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_data_is_ignored() {
+        // 1x1 pixels, 2 frames of 10 ms, followed by a padding byte
+        let data = [
+            b'H', b'7', b'5', b'A', 1, 0, 1, 0, 2, 0, // header
+            10, 0, 1, 255, 0, 0, // red
+            10, 0, 1, 0, 0, 255, // blue
+            0xff,
+        ];
+        let mut player = Player::new(Animation::new(&data).unwrap());
+        assert!(player.update(0));
+        assert_eq!(player.offset, HEADER_LEN);
+        assert!(player.update(10));
+        assert_eq!(player.offset, HEADER_LEN + 6);
+        assert!(player.update(20));
+        assert_eq!(player.offset, HEADER_LEN);
+        assert!(!player.update(25));
+    }
+}
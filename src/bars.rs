@@ -0,0 +1,105 @@
+//! Spectrum analyzer bars, e.g. for showing audio levels
+//!
+//! ```ignore
+//! let mut bars = Bars::<_, 16>::new(Rgb565::GREEN).peaks(Rgb565::RED);
+//! let area = display.bounding_box();
+//! loop {
+//!     bars.update(&fft_bands);
+//!     display.clear();
+//!     bars.draw(&mut display, area).unwrap();
+//!     display.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+
+/// `N` vertical bars growing from the bottom, with optional peak markers
+pub struct Bars<C, const N: usize> {
+    pub color: C,
+    /// Color of the line marking the recent maximum of every bar
+    pub peak_color: Option<C>,
+    /// Number of updates a peak stays before falling
+    pub peak_hold: u8,
+    /// Amount a peak falls per update after the hold time
+    pub peak_decay: u8,
+    /// Empty columns between the bars
+    pub gap: u32,
+    levels: [u8; N],
+    peaks: [u8; N],
+    /// Remaining updates until the peaks fall
+    hold: [u8; N],
+}
+
+impl<C: PixelColor, const N: usize> Bars<C, N> {
+    /// Create new bars, all empty, without peak markers
+    pub fn new(color: C) -> Self {
+        Self {
+            color,
+            peak_color: None,
+            peak_hold: 30,
+            peak_decay: 4,
+            gap: 1,
+            levels: [0; N],
+            peaks: [0; N],
+            hold: [0; N],
+        }
+    }
+
+    /// Mark the peaks with `color`
+    pub fn peaks(mut self, color: C) -> Self {
+        self.peak_color = Some(color);
+        self
+    }
+
+    /// Set the levels of the bars, 255 being the full height
+    ///
+    /// Missing levels are set to 0, extra ones are ignored.
+    pub fn update(&mut self, levels: &[u8]) {
+        for i in 0..N {
+            let level = levels.get(i).copied().unwrap_or(0);
+            self.levels[i] = level;
+            if level >= self.peaks[i] {
+                self.peaks[i] = level;
+                self.hold[i] = self.peak_hold;
+            } else if self.hold[i] > 0 {
+                self.hold[i] -= 1;
+            } else {
+                self.peaks[i] = self.peaks[i].saturating_sub(self.peak_decay).max(level);
+            }
+        }
+    }
+
+    /// Draw the bars into `area`
+    ///
+    /// The bars are spread evenly over the width, so they differ by at most one
+    /// column. Only the bars are drawn, so the area should be cleared
+    /// beforehand.
+    pub fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let Size { width, height } = area.size;
+        // Number of rows lit for a level
+        let rows = |level: u8| (level as u32 * height + 127) / 255;
+        for i in 0..N {
+            let left = width * i as u32 / N as u32;
+            let right = width * (i as u32 + 1) / N as u32;
+            let bar_width = (right - left).saturating_sub(self.gap).max(1);
+            let bar_rows = rows(self.levels[i]);
+            let bar = Rectangle::new(
+                area.top_left + Point::new(left as i32, (height - bar_rows) as i32),
+                Size::new(bar_width, bar_rows),
+            );
+            target.fill_solid(&bar, self.color)?;
+            let peak_rows = rows(self.peaks[i]);
+            if let (Some(color), true) = (self.peak_color, peak_rows > 0) {
+                let peak = Rectangle::new(
+                    area.top_left + Point::new(left as i32, (height - peak_rows) as i32),
+                    Size::new(bar_width, 1),
+                );
+                target.fill_solid(&peak, color)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+//! Drawing bmp images, e.g. stored in flash
+//!
+//! ```ignore
+//! let logo = Bmp::from_slice(include_bytes!("logo.bmp")).unwrap();
+//! display.draw_bmp(&logo, Point::new(8, 0));
+//! ```
+
+use crate::{Buffer, Hub75, Outputs};
+use core::borrow::BorrowMut;
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+pub use tinybmp::Bmp;
+
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA>
+    Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>
+where
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+{
+    /// Draw `image` with its top left corner at `position`
+    ///
+    /// The pixels are written with their full color depth, independent of
+    /// `COLOR`, and clipped to the drawing area.
+    pub fn draw_bmp(&mut self, image: &Bmp<'_, Rgb888>, position: Point) {
+        self.draw_image(image, position);
+    }
+}
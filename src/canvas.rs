@@ -0,0 +1,78 @@
+//! Drawing area larger than a single display, e.g. for video walls
+//!
+//! Every controller draws the whole scene into a `Canvas` and copies only its
+//! own part to the display it drives.
+//!
+//! ```ignore
+//! let mut canvas = Canvas::<256, 64>::new();
+//! draw_scene(&mut canvas);
+//! // This controller drives the panels in the lower right corner
+//! canvas.flush(&mut display, Point::new(128, 32)).unwrap();
+//! ```
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+
+/// Pixel data of a `WIDTH` x `HEIGHT` drawing area
+pub struct Canvas<const WIDTH: usize, const HEIGHT: usize> {
+    data: [[Rgb888; WIDTH]; HEIGHT],
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Canvas<WIDTH, HEIGHT> {
+    /// Create a new canvas, with every pixel black
+    pub fn new() -> Self {
+        Self {
+            data: [[Rgb888::BLACK; WIDTH]; HEIGHT],
+        }
+    }
+
+    /// Copy the part of the canvas starting at `offset` to `target`
+    ///
+    /// The part has the size of `target` and is drawn through its rotation and
+    /// multiplexing. Areas outside of the canvas aren't drawn.
+    pub fn flush<D>(&self, target: &mut D, offset: Point) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+        D::Color: From<Rgb888>,
+    {
+        let target_area = target.bounding_box();
+        let area = Rectangle::new(offset, target_area.size).intersection(&self.bounding_box());
+        let pixels = area.points().map(|point| {
+            let color = self.data[point.y as usize][point.x as usize];
+            Pixel(point - offset + target_area.top_left, color.into())
+        });
+        target.draw_iter(pixels)
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> Default for Canvas<WIDTH, HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> DrawTarget for Canvas<WIDTH, HEIGHT> {
+    type Color = Rgb888;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if coord.x < 0 || coord.y < 0 {
+                continue;
+            }
+            let (x, y) = (coord.x as usize, coord.y as usize);
+            if x < WIDTH && y < HEIGHT {
+                self.data[y][x] = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize> OriginDimensions for Canvas<WIDTH, HEIGHT> {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
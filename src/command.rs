@@ -0,0 +1,175 @@
+//! Drawing commands received from a pc or another controller, e.g. over a
+//! uart or i2c
+//!
+//! Every command starts with a letter, followed by its arguments. Coordinates
+//! and sizes are 16 bit little endian, colors are red, green and blue bytes:
+//!
+//! | Command    | Bytes                                                  |
+//! |------------|--------------------------------------------------------|
+//! | set pixel  | `P`, x, y, color                                       |
+//! | fill rect  | `R`, x, y, width, height, color                        |
+//! | draw text  | `T`, x, y, color, spacing, length, text (utf-8)        |
+//! | show       | `S`                                                    |
+//!
+//! The spacing of the text is 0 for `Spacing::Monospace` and 1 for
+//! `Spacing::Proportional`, the length is a single byte. Bytes not starting
+//! a command are skipped, so the receiver finds the next command after
+//! garbage, e.g. after connecting in the middle of a command.
+//!
+//! ```ignore
+//! let mut receiver = CommandReceiver::new();
+//! loop {
+//!     match receiver.read(&mut serial, &mut display) {
+//!         Ok(true) => display.swap_buffers(),
+//!         Ok(false) | Err(nb::Error::WouldBlock) => {}
+//!         Err(nb::Error::Other(e)) => panic!("{:?}", e),
+//!     }
+//!     display.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use crate::text::{draw_text, Spacing};
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+use embedded_hal_nb::serial;
+
+/// First bytes of the commands
+pub const SET_PIXEL: u8 = b'P';
+pub const FILL_RECT: u8 = b'R';
+pub const DRAW_TEXT: u8 = b'T';
+pub const SHOW: u8 = b'S';
+
+/// Arguments of a text command before the text itself
+const TEXT_HEADER: usize = 9;
+
+/// Errors of `CommandReceiver::read`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadError<S, D> {
+    /// Reading from the serial port failed
+    Serial(S),
+    /// Drawing the command failed
+    Draw(D),
+}
+
+/// Draws received commands to a draw target
+pub struct CommandReceiver {
+    /// Command being received, `None` while waiting for the next one
+    command: Option<u8>,
+    /// Arguments of the command received so far
+    arguments: [u8; TEXT_HEADER + 255],
+    received: usize,
+}
+
+impl CommandReceiver {
+    /// Create a new receiver, waiting for a command
+    pub fn new() -> Self {
+        Self {
+            command: None,
+            arguments: [0; TEXT_HEADER + 255],
+            received: 0,
+        }
+    }
+
+    /// Drop the command being received and wait for the next one
+    pub fn reset(&mut self) {
+        self.command = None;
+        self.received = 0;
+    }
+
+    /// Handle a received byte, drawing the command once it's complete
+    ///
+    /// Returns `true` when a show command was received, the frame drawn so far
+    /// should then be shown, e.g. with `swap_buffers`.
+    pub fn feed<D>(&mut self, byte: u8, target: &mut D) -> Result<bool, D::Error>
+    where
+        D: DrawTarget,
+        D::Color: From<Rgb888>,
+    {
+        let command = match self.command {
+            Some(command) => {
+                self.arguments[self.received] = byte;
+                self.received += 1;
+                command
+            }
+            None => match byte {
+                SET_PIXEL | FILL_RECT | DRAW_TEXT | SHOW => {
+                    self.command = Some(byte);
+                    byte
+                }
+                _ => return Ok(false),
+            },
+        };
+        if self.received < self.length(command) {
+            return Ok(false);
+        }
+        self.reset();
+        let arguments = &self.arguments;
+        let number = |i: usize| i16::from_le_bytes([arguments[i], arguments[i + 1]]);
+        let color = |i: usize| Rgb888::new(arguments[i], arguments[i + 1], arguments[i + 2]);
+        let position = Point::new(number(0) as i32, number(2) as i32);
+        match command {
+            SET_PIXEL => Pixel(position, color(4).into()).draw(target)?,
+            FILL_RECT => {
+                let size = Size::new(number(4) as u16 as u32, number(6) as u16 as u32);
+                target.fill_solid(&Rectangle::new(position, size), color(8).into())?
+            }
+            DRAW_TEXT => {
+                let spacing = match arguments[7] {
+                    0 => Spacing::Monospace,
+                    _ => Spacing::Proportional,
+                };
+                let text = &arguments[TEXT_HEADER..TEXT_HEADER + arguments[8] as usize];
+                // Invalid utf-8 is cut off instead of dropping the whole text
+                let text = match core::str::from_utf8(text) {
+                    Ok(text) => text,
+                    Err(e) => core::str::from_utf8(&text[..e.valid_up_to()]).unwrap(),
+                };
+                draw_text(target, position, text, color(4).into(), spacing)?;
+            }
+            _ => return Ok(true),
+        }
+        Ok(false)
+    }
+
+    /// Read a byte from `serial` and handle it
+    ///
+    /// Returns `true` when a show command was received. Read errors, e.g.
+    /// overruns, drop the current command.
+    pub fn read<S, D>(
+        &mut self,
+        serial: &mut S,
+        target: &mut D,
+    ) -> nb::Result<bool, ReadError<S::Error, D::Error>>
+    where
+        S: serial::Read<u8>,
+        D: DrawTarget,
+        D::Color: From<Rgb888>,
+    {
+        match serial.read() {
+            Ok(byte) => self
+                .feed(byte, target)
+                .map_err(|e| nb::Error::Other(ReadError::Draw(e))),
+            Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
+            Err(nb::Error::Other(e)) => {
+                self.reset();
+                Err(nb::Error::Other(ReadError::Serial(e)))
+            }
+        }
+    }
+
+    /// Number of argument bytes of `command`, as far as they are known
+    fn length(&self, command: u8) -> usize {
+        match command {
+            SET_PIXEL => 7,
+            FILL_RECT => 11,
+            DRAW_TEXT if self.received < TEXT_HEADER => TEXT_HEADER,
+            DRAW_TEXT => TEXT_HEADER + self.arguments[8] as usize,
+            _ => 0,
+        }
+    }
+}
+
+impl Default for CommandReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
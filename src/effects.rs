@@ -0,0 +1,234 @@
+//! Classic demo effects, e.g. for testing new panels
+//!
+//! The effects draw to any draw target, so they exercise the whole pipeline of
+//! the display including its rotation and multiplexing.
+//!
+//! ```ignore
+//! let mut fire = Fire::<64, 32>::new(1);
+//! loop {
+//!     plasma(&mut display, millis()).unwrap();
+//!     // or
+//!     fire.update();
+//!     fire.draw(&mut display).unwrap();
+//!     display.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+
+/// Sine of `phase / 256` of a full turn, scaled to 1-255
+///
+/// Approximated with parabolas, close enough for effects.
+fn sin8(phase: u8) -> u8 {
+    let x = (phase & 127) as u32;
+    let value = (x * (128 - x) / 32).min(127) as u8;
+    if phase < 128 {
+        128 + value
+    } else {
+        128 - value
+    }
+}
+
+/// Fully saturated color of `hue`, going from red over green and blue back to red
+fn wheel(hue: u8) -> Rgb888 {
+    let hue = hue as u16 * 3;
+    let x = (hue % 256) as u8;
+    match hue / 256 {
+        0 => Rgb888::new(255 - x, x, 0),
+        1 => Rgb888::new(0, 255 - x, x),
+        _ => Rgb888::new(x, 0, 255 - x),
+    }
+}
+
+/// Xorshift random numbers, good enough for effects
+fn random(state: &mut u32) -> u32 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *state = x;
+    x
+}
+
+/// Draw every pixel of `target` with the color of its position relative to the
+/// top left corner
+fn draw_each<D>(target: &mut D, color: impl Fn(i32, i32) -> Rgb888) -> Result<(), D::Error>
+where
+    D: DrawTarget,
+    D::Color: From<Rgb888>,
+{
+    let area = target.bounding_box();
+    let pixels = area.points().map(|point| {
+        let Point { x, y } = point - area.top_left;
+        Pixel(point, color(x, y).into())
+    });
+    target.draw_iter(pixels)
+}
+
+/// Draw overlapping waves of color, moving with `time_ms`
+pub fn plasma<D>(target: &mut D, time_ms: u32) -> Result<(), D::Error>
+where
+    D: DrawTarget,
+    D::Color: From<Rgb888>,
+{
+    let t = time_ms / 16;
+    draw_each(target, |x, y| {
+        let value = sin8((x * 8 + t as i32) as u8) as u16
+            + sin8((y * 8 + (t * 2 / 3) as i32) as u8) as u16
+            + sin8(((x + y) * 6 + (t * 3 / 2) as i32) as u8) as u16;
+        wheel(((value / 3) as u8).wrapping_add((t / 4) as u8))
+    })
+}
+
+/// Draw diagonal rainbow stripes, moving with `time_ms`
+pub fn rainbow_wave<D>(target: &mut D, time_ms: u32) -> Result<(), D::Error>
+where
+    D: DrawTarget,
+    D::Color: From<Rgb888>,
+{
+    let width = target.bounding_box().size.width.max(1) as i32;
+    let t = (time_ms / 8) as i32;
+    draw_each(target, |x, y| wheel((x * 256 / width - y * 4 + t) as u8))
+}
+
+/// Flames rising from the bottom of a `W` x `H` area
+pub struct Fire<const W: usize, const H: usize> {
+    heat: [[u8; W]; H],
+    /// Maximum heat lost per row, higher values make smaller flames
+    ///
+    /// Defaults to flames reaching about the top of the area.
+    pub cooling: u8,
+    random: u32,
+}
+
+impl<const W: usize, const H: usize> Fire<W, H> {
+    /// Create a new, cold fire, `seed` selects the flames
+    pub fn new(seed: u32) -> Self {
+        const { assert!(W > 0 && H > 0, "a fire needs at least one pixel") }
+        Self {
+            heat: [[0; W]; H],
+            cooling: (512 / H).clamp(1, 255) as u8,
+            random: seed.max(1),
+        }
+    }
+
+    /// Move the flames up by one row and light the bottom one again
+    pub fn update(&mut self) {
+        for y in 0..H.saturating_sub(1) {
+            let below = y + 1;
+            let further = (y + 2).min(H - 1);
+            for x in 0..W {
+                let left = x.saturating_sub(1);
+                let right = (x + 1).min(W - 1);
+                let sum = self.heat[below][left] as u16
+                    + self.heat[below][x] as u16
+                    + self.heat[below][right] as u16
+                    + self.heat[further][x] as u16;
+                let cooling = (random(&mut self.random) % (self.cooling as u32 + 1)) as u8;
+                self.heat[y][x] = ((sum / 4) as u8).saturating_sub(cooling);
+            }
+        }
+        if let Some(bottom) = self.heat.last_mut() {
+            for heat in bottom.iter_mut() {
+                *heat = 160 + (random(&mut self.random) % 96) as u8;
+            }
+        }
+    }
+
+    /// Draw the fire to the top left corner of `target`
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+        D::Color: From<Rgb888>,
+    {
+        let top_left = target.bounding_box().top_left;
+        let pixels = self.heat.iter().enumerate().flat_map(|(y, row)| {
+            row.iter().enumerate().map(move |(x, &heat)| {
+                // Black over red and yellow to white
+                let heat = heat as u16 * 3;
+                let color = Rgb888::new(
+                    heat.min(255) as u8,
+                    heat.saturating_sub(255).min(255) as u8,
+                    heat.saturating_sub(510) as u8,
+                );
+                Pixel(top_left + Point::new(x as i32, y as i32), color.into())
+            })
+        });
+        target.draw_iter(pixels)
+    }
+}
+
+/// Conway's Game of Life on a `W` x `H` grid, wrapping around at the edges
+pub struct Life<const W: usize, const H: usize> {
+    cells: [[bool; W]; H],
+    random: u32,
+}
+
+impl<const W: usize, const H: usize> Life<W, H> {
+    /// Create a new grid with random cells, `seed` selects the pattern
+    pub fn new(seed: u32) -> Self {
+        const { assert!(W > 0 && H > 0, "a grid needs at least one cell") }
+        let mut life = Self {
+            cells: [[false; W]; H],
+            random: seed.max(1),
+        };
+        life.randomize();
+        life
+    }
+
+    /// Fill the grid with random cells again, e.g. once it stopped changing
+    pub fn randomize(&mut self) {
+        for row in self.cells.iter_mut() {
+            for cell in row.iter_mut() {
+                *cell = random(&mut self.random) & 3 == 0;
+            }
+        }
+    }
+
+    /// Advance by one generation, returns whether any cell changed
+    pub fn step(&mut self) -> bool {
+        let old = self.cells;
+        // Offsets of the neighbors, wrapping around
+        let (left, up) = (W - 1, H - 1);
+        let offsets = [
+            (left, up),
+            (0, up),
+            (1, up),
+            (left, 0),
+            (1, 0),
+            (left, 1),
+            (0, 1),
+            (1, 1),
+        ];
+        let mut changed = false;
+        for y in 0..H {
+            for x in 0..W {
+                let neighbors = offsets
+                    .iter()
+                    .filter(|(dx, dy)| old[(y + dy) % H][(x + dx) % W])
+                    .count();
+                let alive = matches!((old[y][x], neighbors), (true, 2) | (_, 3));
+                changed |= alive != old[y][x];
+                self.cells[y][x] = alive;
+            }
+        }
+        changed
+    }
+
+    /// Draw the living cells in `color` and the others black, to the top left
+    /// corner of `target`
+    pub fn draw<D>(&self, target: &mut D, color: Rgb888) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+        D::Color: From<Rgb888>,
+    {
+        let top_left = target.bounding_box().top_left;
+        let pixels = self.cells.iter().enumerate().flat_map(|(y, row)| {
+            row.iter().enumerate().map(move |(x, &alive)| {
+                let color = if alive { color } else { Rgb888::BLACK };
+                Pixel(top_left + Point::new(x as i32, y as i32), color.into())
+            })
+        });
+        target.draw_iter(pixels)
+    }
+}
@@ -0,0 +1,32 @@
+//! Effects applied to every pixel during output, e.g. for a night mode
+//!
+//! The buffer is left as it is, so the effect can change from frame to frame
+//! without redrawing anything.
+//!
+//! ```ignore
+//! static DIM: AtomicU8 = AtomicU8::new(255);
+//! static BREATHING: fn(usize, usize, Rgb888) -> Rgb888 = |_, _, color| {
+//!     let level = DIM.load(Ordering::Relaxed) as u16;
+//!     let scale = |value: u8| (value as u16 * level / 255) as u8;
+//!     Rgb888::new(scale(color.r()), scale(color.g()), scale(color.b()))
+//! };
+//! display.set_output_filter(Some(&BREATHING));
+//! ```
+
+use embedded_graphics::pixelcolor::Rgb888;
+
+/// Transforms the color of a pixel during output
+///
+/// Implemented for closures and functions taking the column, the row and the
+/// color. The position is the one in the buffer, so it ignores the rotation
+/// and multiplexing of the display.
+pub trait OutputFilter {
+    /// Color to output for the pixel at `(x, y)`, given its drawn color
+    fn apply(&self, x: usize, y: usize, color: Rgb888) -> Rgb888;
+}
+
+impl<T: Fn(usize, usize, Rgb888) -> Rgb888> OutputFilter for T {
+    fn apply(&self, x: usize, y: usize, color: Rgb888) -> Rgb888 {
+        self(x, y, color)
+    }
+}
@@ -0,0 +1,209 @@
+//! Pixel storage independent of the display
+//!
+//! A `Framebuffer` can be drawn to in one task or on one core and shown with
+//! `Hub75::output_frame` in another, without borrowing the display.
+//!
+//! ```ignore
+//! let mut frame = Framebuffer::<16, 64>::new();
+//! Circle::new(Point::new(8, 8), 16)
+//!     .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN))
+//!     .draw(&mut frame)
+//!     .unwrap();
+//! display.output_frame(&frame, &mut delay).unwrap();
+//! ```
+
+use crate::{Buffer, Frame};
+use core::marker::PhantomData;
+use embedded_graphics::{
+    pixelcolor::{Rgb565, Rgb888},
+    prelude::*,
+};
+
+/// Pixel data of a whole frame
+///
+/// Drawing ignores the rotation and multiplexing of the display, the pixels
+/// are stored as they are shifted out.
+pub struct Framebuffer<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR = Rgb565> {
+    data: Buffer<NUM_ROWS, ROW_LENGTH>,
+    color: PhantomData<COLOR>,
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR>
+    Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>
+{
+    /// Create a new buffer, with every pixel turned off
+    pub const fn new() -> Self {
+        Self {
+            data: [[(0, 0, 0, 0, 0, 0); ROW_LENGTH]; NUM_ROWS],
+            color: PhantomData,
+        }
+    }
+
+    /// Turn every pixel off
+    pub fn clear(&mut self) {
+        for row in self.data.iter_mut() {
+            for e in row.iter_mut() {
+                *e = (0, 0, 0, 0, 0, 0);
+            }
+        }
+    }
+
+    pub fn buffer(&self) -> &Buffer<NUM_ROWS, ROW_LENGTH> {
+        &self.data
+    }
+
+    pub fn buffer_mut(&mut self) -> &mut Buffer<NUM_ROWS, ROW_LENGTH> {
+        &mut self.data
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR> Default
+    for Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR> Frame<NUM_ROWS, ROW_LENGTH>
+    for Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>
+{
+    fn pixels(&self, row: usize, column: usize) -> (u8, u8, u8, u8, u8, u8) {
+        self.data[row][column]
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR> DrawTarget
+    for Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>
+where
+    COLOR: PixelColor + Into<Rgb888>,
+{
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if coord.x < 0 || coord.y < 0 {
+                continue;
+            }
+            let (x, y) = (coord.x as usize, coord.y as usize);
+            if x >= ROW_LENGTH || y >= NUM_ROWS * 2 {
+                continue;
+            }
+            let color: Rgb888 = color.into();
+            let data = &mut self.data[y % NUM_ROWS][x];
+            if y >= NUM_ROWS {
+                data.3 = color.r();
+                data.4 = color.g();
+                data.5 = color.b();
+            } else {
+                data.0 = color.r();
+                data.1 = color.g();
+                data.2 = color.b();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR> OriginDimensions
+    for Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>
+{
+    fn size(&self) -> Size {
+        Size::new(ROW_LENGTH as u32, (NUM_ROWS * 2) as u32)
+    }
+}
+
+/// Pixel data of a whole frame in a plain row-major layout
+///
+/// Every pixel is three bytes, red, green and blue, and the rows follow each
+/// other from the top, so external renderers can write to `as_bytes_mut`
+/// directly. Splitting the halves happens during output, which takes a bit
+/// longer than with a `Framebuffer`.
+pub struct RgbFramebuffer<const NUM_ROWS: usize, const ROW_LENGTH: usize> {
+    /// The upper and lower half
+    data: [[[[u8; 3]; ROW_LENGTH]; NUM_ROWS]; 2],
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> RgbFramebuffer<NUM_ROWS, ROW_LENGTH> {
+    /// Create a new buffer, with every pixel turned off
+    pub const fn new() -> Self {
+        Self {
+            data: [[[[0; 3]; ROW_LENGTH]; NUM_ROWS]; 2],
+        }
+    }
+
+    /// Turn every pixel off
+    pub fn clear(&mut self) {
+        self.as_bytes_mut().fill(0);
+    }
+
+    /// The pixel data, `NUM_ROWS * 2 * ROW_LENGTH * 3` bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data.as_flattened().as_flattened().as_flattened()
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.data
+            .as_flattened_mut()
+            .as_flattened_mut()
+            .as_flattened_mut()
+    }
+
+    /// The pixels of row `y`, counting from the top of the whole frame
+    pub fn row_mut(&mut self, y: usize) -> &mut [[u8; 3]; ROW_LENGTH] {
+        &mut self.data[y / NUM_ROWS][y % NUM_ROWS]
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Default
+    for RgbFramebuffer<NUM_ROWS, ROW_LENGTH>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Frame<NUM_ROWS, ROW_LENGTH>
+    for RgbFramebuffer<NUM_ROWS, ROW_LENGTH>
+{
+    fn pixels(&self, row: usize, column: usize) -> (u8, u8, u8, u8, u8, u8) {
+        let [r1, g1, b1] = self.data[0][row][column];
+        let [r2, g2, b2] = self.data[1][row][column];
+        (r1, g1, b1, r2, g2, b2)
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> DrawTarget
+    for RgbFramebuffer<NUM_ROWS, ROW_LENGTH>
+{
+    type Color = Rgb888;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if coord.x < 0 || coord.y < 0 {
+                continue;
+            }
+            let (x, y) = (coord.x as usize, coord.y as usize);
+            if x < ROW_LENGTH && y < NUM_ROWS * 2 {
+                self.row_mut(y)[x] = [color.r(), color.g(), color.b()];
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> OriginDimensions
+    for RgbFramebuffer<NUM_ROWS, ROW_LENGTH>
+{
+    fn size(&self) -> Size {
+        Size::new(ROW_LENGTH as u32, (NUM_ROWS * 2) as u32)
+    }
+}
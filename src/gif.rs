@@ -0,0 +1,499 @@
+//! Playing gif animations, e.g. stored in flash
+//!
+//! The frames are decoded while drawing, without allocating. Frames covering
+//! only part of the image are drawn over the previous one, so the target has
+//! to keep its content between frames, like the display does.
+//!
+//! ```ignore
+//! static DATA: &[u8] = include_bytes!("animation.gif");
+//! let mut player = Player::new(Gif::new(DATA).unwrap());
+//! loop {
+//!     if player.update(millis()) {
+//!         player.draw(&mut display).unwrap();
+//!     }
+//!     display.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+
+/// Header and logical screen descriptor
+const HEADER_LEN: usize = 13;
+/// Number of codes of the lzw table, codes have at most 12 bits
+const MAX_CODES: usize = 1 << 12;
+
+/// Problems found in the gif data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The data doesn't start with `GIF87a` or `GIF89a` or has no frames
+    InvalidHeader,
+    /// The data ends within a block
+    Truncated,
+    /// A block has an unknown type or invalid values, e.g. a frame without
+    /// colors
+    InvalidBlock,
+}
+
+/// A validated gif
+#[derive(Clone, Copy, Debug)]
+pub struct Gif<'a> {
+    data: &'a [u8],
+    size: Size,
+    /// Global color table, 3 bytes per color
+    colors: &'a [u8],
+    first: Frame<'a>,
+    frames: u32,
+}
+
+impl<'a> Gif<'a> {
+    /// Check the structure of the gif
+    ///
+    /// The image data itself is only decoded while drawing. Data after the
+    /// trailer is ignored, a missing trailer is tolerated.
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_LEN || !matches!(&data[..6], b"GIF87a" | b"GIF89a") {
+            return Err(Error::InvalidHeader);
+        }
+        let value = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let (colors, start) = color_table(data, HEADER_LEN, data[10])?;
+        let first = frame(data, colors, start)?.ok_or(Error::InvalidHeader)?;
+        let mut gif = Self {
+            data,
+            size: Size::new(value(6) as u32, value(8) as u32),
+            colors,
+            first,
+            frames: 1,
+        };
+        let mut offset = first.end;
+        while let Some(frame) = gif.frame(offset)? {
+            gif.frames += 1;
+            offset = frame.end;
+        }
+        Ok(gif)
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Number of frames
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// The frame starting at `offset`, `None` after the last one
+    fn frame(&self, offset: usize) -> Result<Option<Frame<'a>>, Error> {
+        frame(self.data, self.colors, offset)
+    }
+}
+
+/// Position, colors and timing of a frame
+#[derive(Clone, Copy, Debug)]
+struct Frame<'a> {
+    area: Rectangle,
+    /// Color table of the frame, 3 bytes per color
+    colors: &'a [u8],
+    transparent: Option<u8>,
+    /// Time to show the frame in ms
+    delay: u32,
+    /// Whether the area is turned off before drawing the next frame
+    dispose: bool,
+    interlaced: bool,
+    /// Number of bits of the color indices, the lzw codes start one longer
+    code_size: u8,
+    /// Start of the lzw data
+    start: usize,
+    /// Start of the next frame
+    end: usize,
+}
+
+/// Parse the frame starting at `offset`, including its extensions
+///
+/// `colors` is the global color table, used by frames without their own.
+fn frame<'a>(
+    data: &'a [u8],
+    colors: &'a [u8],
+    mut offset: usize,
+) -> Result<Option<Frame<'a>>, Error> {
+    let (mut delay, mut transparent, mut dispose) = (0, None, false);
+    loop {
+        match data.get(offset) {
+            None | Some(0x3b) => return Ok(None),
+            Some(0x21) => {
+                let label = *data.get(offset + 1).ok_or(Error::Truncated)?;
+                offset += 2;
+                // Graphic control extension, applying to the next image
+                if label == 0xf9 {
+                    let control = data.get(offset..offset + 5).ok_or(Error::Truncated)?;
+                    if control[0] != 4 {
+                        return Err(Error::InvalidBlock);
+                    }
+                    dispose = (control[1] >> 2) & 7 == 2;
+                    delay = u16::from_le_bytes([control[2], control[3]]) as u32 * 10;
+                    transparent = (control[1] & 1 != 0).then_some(control[4]);
+                }
+                offset = skip_blocks(data, offset)?;
+            }
+            Some(0x2c) => {
+                let descriptor = data.get(offset + 1..offset + 10).ok_or(Error::Truncated)?;
+                let value = |i: usize| u16::from_le_bytes([descriptor[i], descriptor[i + 1]]);
+                let area = Rectangle::new(
+                    Point::new(value(0) as i32, value(2) as i32),
+                    Size::new(value(4) as u32, value(6) as u32),
+                );
+                let (local, start) = color_table(data, offset + 10, descriptor[8])?;
+                let colors = if local.is_empty() { colors } else { local };
+                let code_size = *data.get(start).ok_or(Error::Truncated)?;
+                if colors.is_empty() || !(1..=8).contains(&code_size) {
+                    return Err(Error::InvalidBlock);
+                }
+                return Ok(Some(Frame {
+                    area,
+                    colors,
+                    transparent,
+                    // Like browsers, which show these frames for 100 ms
+                    delay: if delay <= 10 { 100 } else { delay },
+                    dispose,
+                    interlaced: descriptor[8] & 0x40 != 0,
+                    code_size,
+                    start: start + 1,
+                    end: skip_blocks(data, start + 1)?,
+                }));
+            }
+            Some(_) => return Err(Error::InvalidBlock),
+        }
+    }
+}
+
+/// The color table at `offset` if `flags` has one, and the offset after it
+fn color_table(data: &[u8], offset: usize, flags: u8) -> Result<(&[u8], usize), Error> {
+    if flags & 0x80 == 0 {
+        return Ok((&[], offset));
+    }
+    let end = offset + (3 << ((flags & 7) + 1));
+    let colors = data.get(offset..end).ok_or(Error::Truncated)?;
+    Ok((colors, end))
+}
+
+/// Find the end of the data sub-blocks starting at `offset`
+fn skip_blocks(data: &[u8], mut offset: usize) -> Result<usize, Error> {
+    loop {
+        let length = *data.get(offset).ok_or(Error::Truncated)? as usize;
+        offset += 1 + length;
+        if length == 0 {
+            return Ok(offset);
+        }
+    }
+}
+
+/// Row of the `row`th stored row of an interlaced frame of `height` rows
+fn interlaced_row(mut row: usize, height: usize) -> usize {
+    // Every 8th row from 0, every 8th from 4, every 4th from 2, then the rest
+    for (start, step) in [(0, 8), (4, 8), (2, 4), (1, 2)] {
+        let rows = height.saturating_sub(start).div_ceil(step);
+        if row < rows {
+            return start + row * step;
+        }
+        row -= rows;
+    }
+    height + row
+}
+
+/// Reads the lzw codes from the data sub-blocks of a frame
+struct Codes<'a> {
+    data: &'a [u8],
+    offset: usize,
+    /// Bytes left in the current sub-block
+    left: usize,
+    bits: u32,
+    count: u8,
+}
+
+impl Codes<'_> {
+    /// The next code of `size` bits, `None` at the end of the data
+    fn read(&mut self, size: u8) -> Option<u16> {
+        while self.count < size {
+            if self.left == 0 {
+                self.left = *self.data.get(self.offset)? as usize;
+                self.offset += 1;
+                if self.left == 0 {
+                    return None;
+                }
+            }
+            let byte = *self.data.get(self.offset)?;
+            self.offset += 1;
+            self.left -= 1;
+            self.bits |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let code = (self.bits & ((1 << size) - 1)) as u16;
+        self.bits >>= size;
+        self.count -= size;
+        Some(code)
+    }
+}
+
+/// Strings of the lzw codes, every code is its prefix code followed by a color
+struct Table {
+    prefix: [u16; MAX_CODES],
+    suffix: [u8; MAX_CODES],
+}
+
+impl Table {
+    /// Number of colors of `code`, codes below `clear` are single colors
+    fn length(&self, mut code: u16, clear: u16) -> usize {
+        let mut length = 1;
+        while code >= clear {
+            code = self.prefix[code as usize];
+            length += 1;
+        }
+        length
+    }
+
+    /// First color of `code`
+    fn first(&self, mut code: u16, clear: u16) -> u8 {
+        while code >= clear {
+            code = self.prefix[code as usize];
+        }
+        code as u8
+    }
+
+    /// Last color of `code`
+    fn last(&self, code: u16, clear: u16) -> u8 {
+        if code < clear {
+            code as u8
+        } else {
+            self.suffix[code as usize]
+        }
+    }
+}
+
+/// Plays a gif in a loop
+///
+/// Holds the 12 kB table for decoding, so it's best kept in a static or at
+/// the start of `main`.
+pub struct Player<'a> {
+    gif: Gif<'a>,
+    frame: Frame<'a>,
+    /// Area to turn off before drawing the current frame
+    disposed: Option<Rectangle>,
+    /// Time the current frame was first shown
+    shown: Option<u32>,
+    table: Table,
+}
+
+impl<'a> Player<'a> {
+    /// Create a new player, starting at the first frame
+    pub fn new(gif: Gif<'a>) -> Self {
+        Self {
+            gif,
+            frame: gif.first,
+            disposed: Some(Rectangle::new(Point::zero(), gif.size)),
+            shown: None,
+            table: Table {
+                prefix: [0; MAX_CODES],
+                suffix: [0; MAX_CODES],
+            },
+        }
+    }
+
+    /// Start over at the first frame
+    pub fn reset(&mut self) {
+        self.frame = self.gif.first;
+        self.disposed = Some(Rectangle::new(Point::zero(), self.gif.size));
+        self.shown = None;
+    }
+
+    /// Advance the animation to the time `now`, in ms
+    ///
+    /// The clock may wrap around. Returns `true` if another frame has to be
+    /// drawn. A gif with a single frame is only drawn once.
+    pub fn update(&mut self, now: u32) -> bool {
+        let shown = match self.shown {
+            Some(shown) => shown,
+            None => {
+                self.shown = Some(now);
+                return true;
+            }
+        };
+        let delay = self.frame.delay;
+        if self.gif.frames == 1 || now.wrapping_sub(shown) < delay {
+            return false;
+        }
+        self.disposed = self.frame.dispose.then_some(self.frame.area);
+        // Validated in `Gif::new`
+        match self.gif.frame(self.frame.end) {
+            Ok(Some(frame)) => self.frame = frame,
+            _ => {
+                self.frame = self.gif.first;
+                self.disposed = Some(Rectangle::new(Point::zero(), self.gif.size));
+            }
+        }
+        // Keep the timing when frames are drawn late
+        self.shown = Some(shown.wrapping_add(delay));
+        true
+    }
+
+    /// Draw the current frame to the top left corner of `target`
+    ///
+    /// Transparent pixels are left as they are. Areas the gif restores to
+    /// the background are turned off. Invalid image data ends the frame, the
+    /// rest of it is left as it is.
+    pub fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+        D::Color: From<Rgb888>,
+    {
+        if let Some(area) = self.disposed {
+            target.fill_solid(&area, Rgb888::BLACK.into())?;
+        }
+        let frame = self.frame;
+        let table = &mut self.table;
+        let (width, height) = (
+            frame.area.size.width as usize,
+            frame.area.size.height as usize,
+        );
+        let pixels = width * height;
+        let clear = 1 << frame.code_size;
+        let mut codes = Codes {
+            data: self.gif.data,
+            offset: frame.start,
+            left: 0,
+            bits: 0,
+            count: 0,
+        };
+        let mut size = frame.code_size + 1;
+        let mut next = clear + 2;
+        let mut previous = None;
+        let mut index = 0;
+        while index < pixels {
+            let code = match codes.read(size) {
+                Some(code) => code,
+                None => break,
+            };
+            if code == clear {
+                size = frame.code_size + 1;
+                next = clear + 2;
+                previous = None;
+                continue;
+            }
+            match previous {
+                None if code < clear => {}
+                Some(previous) if code <= next && code != clear + 1 => {
+                    // A code right after the last one repeats the last string
+                    // with its own first color appended
+                    if (next as usize) < MAX_CODES {
+                        let first = if code < next { code } else { previous };
+                        table.prefix[next as usize] = previous;
+                        table.suffix[next as usize] = table.first(first, clear);
+                        next += 1;
+                        if next == 1 << size && size < 12 {
+                            size += 1;
+                        }
+                    }
+                }
+                // The end code or an invalid code
+                _ => break,
+            }
+            previous = Some(code);
+            // Strings are stored back to front, so they are drawn that way
+            let length = table.length(code, clear);
+            let mut string = code;
+            let colors = (0..length).rev().map(|offset| {
+                let color = table.last(string, clear);
+                string = table.prefix[string as usize];
+                (index + offset, color)
+            });
+            let pixels = colors
+                .filter(|&(i, color)| i < pixels && Some(color) != frame.transparent)
+                .filter_map(|(i, color)| {
+                    let rgb = frame
+                        .colors
+                        .get(color as usize * 3..color as usize * 3 + 3)?;
+                    let row = if frame.interlaced {
+                        interlaced_row(i / width, height)
+                    } else {
+                        i / width
+                    };
+                    let point = frame.area.top_left + Point::new((i % width) as i32, row as i32);
+                    Some(Pixel(point, Rgb888::new(rgb[0], rgb[1], rgb[2]).into()))
+                });
+            target.draw_iter(pixels)?;
+            index += length;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mock_display::MockDisplay;
+
+    /// 4x2 pixels, black, red, green and blue as global colors, 3 frames:
+    /// - the upper row red for 100 ms, turned off afterwards
+    /// - the lower row green for 200 ms
+    /// - a transparent pixel at (0, 1), shown for 100 ms
+    const GIF: &[u8] = &[
+        b'G', b'I', b'F', b'8', b'9', b'a', 4, 0, 2, 0, 0x81, 0, 0, // header
+        0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255, // colors
+        0x21, 0xf9, 4, 0x08, 10, 0, 0, 0, // restore to background, 10 cs
+        0x2c, 0, 0, 0, 0, 4, 0, 1, 0, 0, // 4x1 at (0, 0)
+        2, 2, 0x8c, 0x53, 0, // clear, 1, 6, 1, end
+        0x21, 0xf9, 4, 0x00, 20, 0, 0, 0, // 20 cs
+        0x2c, 0, 0, 1, 0, 4, 0, 1, 0, 0, // 4x1 at (0, 1)
+        2, 2, 0x94, 0x55, 0, // clear, 2, 6, 2, end
+        0x21, 0xf9, 4, 0x01, 0, 0, 0, 0, // transparent 0, no delay
+        0x2c, 0, 0, 1, 0, 1, 0, 1, 0, 0, // 1x1 at (0, 1)
+        2, 2, 0x44, 0x01, 0, // clear, 0, end
+        0x3b,
+    ];
+
+    fn draw(player: &mut Player<'_>, display: &mut MockDisplay<Rgb888>) {
+        display.set_allow_overdraw(true);
+        player.draw(display).unwrap();
+    }
+
+    #[test]
+    fn plays_frames() {
+        let gif = Gif::new(GIF).unwrap();
+        assert_eq!(gif.size(), Size::new(4, 2));
+        assert_eq!(gif.frames(), 3);
+        let mut player = Player::new(gif);
+        let mut display = MockDisplay::new();
+
+        assert!(player.update(0));
+        draw(&mut player, &mut display);
+        display.assert_pattern(&["RRRR", "KKKK"]);
+        assert!(!player.update(99));
+        assert!(player.update(100));
+        draw(&mut player, &mut display);
+        display.assert_pattern(&["KKKK", "GGGG"]);
+        assert!(player.update(300));
+        draw(&mut player, &mut display);
+        display.assert_pattern(&["KKKK", "GGGG"]);
+        assert!(!player.update(399));
+        // Starting over turns everything off first
+        assert!(player.update(400));
+        draw(&mut player, &mut display);
+        display.assert_pattern(&["RRRR", "KKKK"]);
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert_eq!(Gif::new(b"GIF89b").err(), Some(Error::InvalidHeader));
+        assert_eq!(Gif::new(&GIF[..30]).err(), Some(Error::Truncated));
+        let mut data = [0; GIF.len()];
+        data.copy_from_slice(GIF);
+        data[25] = 0x22;
+        assert_eq!(Gif::new(&data).err(), Some(Error::InvalidBlock));
+    }
+
+    #[test]
+    fn interlaced_rows() {
+        let rows = [0, 8, 4, 2, 6, 1, 3, 5, 7, 9];
+        for (stored, &row) in rows.iter().enumerate() {
+            assert_eq!(interlaced_row(stored, 10), row);
+        }
+    }
+}
@@ -1,6 +1,45 @@
 #![no_std]
-use embedded_hal::blocking::delay::DelayUs;
-use embedded_hal::digital::v2::OutputPin;
+#[cfg(any(test, feature = "trace"))]
+extern crate std;
+use core::borrow::BorrowMut;
+use core::marker::PhantomData;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+
+pub mod addressing;
+#[cfg(feature = "anim")]
+pub mod anim;
+pub mod bars;
+#[cfg(feature = "bmp")]
+pub mod bmp;
+pub mod canvas;
+pub mod command;
+#[cfg(feature = "effects")]
+pub mod effects;
+pub mod filter;
+pub mod framebuffer;
+#[cfg(feature = "gif")]
+pub mod gif;
+pub mod multiplexing;
+pub mod palette;
+pub mod pwm;
+#[cfg(target_has_atomic = "32")]
+pub mod queue;
+pub mod receiver;
+pub mod scroller;
+#[cfg(any(test, feature = "simulator"))]
+pub mod simulator;
+pub mod sprite;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod stream;
+pub mod text;
+#[cfg(any(test, feature = "trace"))]
+pub mod trace;
+pub mod transition;
+use addressing::{Addressing, Direct, Signal};
+use filter::OutputFilter;
+use multiplexing::{Multiplexing, Straight, Stripe};
 // Inspired by
 // - https://github.com/polyfloyd/ledcat/blob/master/src/device/hub75.rs
 // - https://github.com/mmou/led-marquee/blob/8c88531a6938edff6db829ca21c15304515874ea/src/hub.rs
@@ -13,29 +52,381 @@ use embedded_hal::digital::v2::OutputPin;
 /// rows by another set (r2, g2, b2). So, the best way to update it is to
 /// show one of the botton and top rows in tandem. The row (between 0-15) is then
 /// selected by the A, B, C, D pins, which are just, as one might expect, the bits 0 to 3.
-/// Pin F is used by the 64x64 display to get 5 bit row addressing (1/32 row scan rate)
+/// Pin E is used by the 64x64 display to get 5 bit row addressing (1/32 row scan rate),
+/// it sits where 32 row panels have a GND pin and is sometimes labelled F
 ///
 /// The display doesn't really do brightness, so we have to do it ourselves, by
 /// rendering the same frame multiple times, with some pixels being turned of if
 /// they are darker (pwm)
+///
+/// The panel geometry is given by the const parameters:
+/// - `NUM_ROWS` is the number of rows selected by the address pins, i.e. half the
+///   height of the panel (16 for 64x32 panels, 32 for 64x64 panels)
+/// - `ROW_LENGTH` is the number of pixels shifted out per row (64 for a single panel).
+///   For daisy-chained panels, this is the combined width, e.g. 192 for three
+///   64x32 panels, which are then drawn to as one wide display
+///
+/// `COLOR` is the color type used for drawing. Any color convertible to `Rgb888`
+/// works, e.g. `Rgb565`, `Rgb555` or `Rgb888` itself.
+///
+/// With two buffers, e.g. from `new_double_buffered`, drawing goes to a back
+/// buffer, which is only shown after calling `swap_buffers`.
+///
+/// `DATA` holds the pixel data, by default inside the struct. With `with_buffer`,
+/// it can be borrowed instead, e.g. from a static in external ram.
+pub struct Hub75<
+    PINS,
+    const NUM_ROWS: usize = 16,
+    const ROW_LENGTH: usize = 64,
+    COLOR = embedded_graphics::pixelcolor::Rgb565,
+    DATA = [Buffer<NUM_ROWS, ROW_LENGTH>; 1],
+> {
+    data: DATA,
+    /// Index of the buffer that's being output
+    front: usize,
+    driver: Driver<PINS, NUM_ROWS, ROW_LENGTH>,
+    layout: Layout<NUM_ROWS, ROW_LENGTH>,
+    /// Whether drawing outside of the drawing area is an error
+    strict_bounds: bool,
+    color: PhantomData<COLOR>,
+}
 
-#[cfg(feature = "size-64x64")]
-const NUM_ROWS: usize = 32;
-#[cfg(not(feature = "size-64x64"))]
-const NUM_ROWS: usize = 16;
+/// A display with a front and a back buffer, see `Hub75::new_double_buffered`
+pub type DoubleBuffered<
+    PINS,
+    const NUM_ROWS: usize = 16,
+    const ROW_LENGTH: usize = 64,
+    COLOR = embedded_graphics::pixelcolor::Rgb565,
+> = Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, [Buffer<NUM_ROWS, ROW_LENGTH>; 2]>;
+
+/// Everything needed for drawing, i.e. how points of the drawing area end up
+/// in the buffer
+///
+/// Kept apart from the display, so a `queue::Drawer` can draw the same way.
+#[derive(Clone, Copy)]
+struct Layout<const NUM_ROWS: usize, const ROW_LENGTH: usize> {
+    multiplexing: &'static (dyn Multiplexing + Sync),
+    rotation: Rotation,
+    /// Mirror the drawing area horizontally and vertically, before rotating it
+    mirror: (bool, bool),
+    blend: Blend,
+    color_mode: PanelColorMode,
+}
 
-pub struct Hub75<PINS> {
-    //       r1, g1, b1, r2, g2, b2, column, row
-    data: [[(u8, u8, u8, u8, u8, u8); 64]; NUM_ROWS],
+/// Everything needed for output
+///
+/// Kept apart from the pixel data, so both can be borrowed at the same time
+struct Driver<PINS, const NUM_ROWS: usize, const ROW_LENGTH: usize> {
     brightness_step: u8,
     brightness_count: u8,
+    /// Lookup tables for r, g, b applied during output
+    color_correction: [&'static [u8; 256]; 3],
+    /// Scales every value during output, 255 is full brightness
+    global_brightness: u8,
+    current_limit: Option<CurrentLimit>,
+    /// Brightness allowed by the current limit for the frame being output
+    limited_brightness: u8,
+    /// Estimated peak current of the last frame at full brightness, in mA
+    estimated_current: u32,
+    /// Highest row current of the frame being output by `output_step`
+    step_peak: u32,
+    /// Scales r, g, b during output, 255 is full brightness
+    white_balance: [u8; 3],
+    /// Order of the channels in the upper and lower half
+    color_order: [ColorOrder; 2],
+    /// Time in µs the output stays off around latching a row
+    latch_blanking: u8,
+    /// Whether oe is high instead of low while the output is enabled
+    oe_inverted: bool,
+    /// Whether lat is pulsed low instead of high
+    lat_inverted: bool,
+    dithering: bool,
+    /// Reorder the pwm cycles, so the on-time of a pixel isn't continuous
+    interleaved: bool,
+    phases: PwmPhases,
+    calibration: Option<PanelCalibration>,
+    /// Frames output so far, selects the dithering pattern
+    frame_count: u8,
+    addressing: &'static (dyn Addressing + Sync),
+    filter: Option<&'static (dyn OutputFilter + Sync)>,
+    /// Buffer column shifted out at every position
+    column_map: Option<&'static [usize]>,
+    /// Whether the output was turned off with `display_off`
+    off: bool,
+    /// Position of `output_step`
+    step_row: usize,
+    step_brightness: u8,
     pins: PINS,
 }
 
+/// Maximum current the display may draw, for `set_current_limit`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CurrentLimit {
+    /// Current of a single fully lit led of one color, in mA
+    pub led_ma: u16,
+    /// Current the supply can provide to the display, in mA
+    pub budget_ma: u32,
+}
+
+/// Upper bound of the work done by one `output_step` call
+///
+/// The time of a call is at most `delay_us` plus the time of `pin_writes` pin
+/// writes and of processing `pixels` pixels, which can be measured once for
+/// the target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepCost {
+    pub pin_writes: usize,
+    pub pixels: usize,
+    pub delay_us: u32,
+}
+
+/// Invalid configurations of the display
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The brightness bits are outside of 1-8
+    InvalidBrightnessBits,
+    /// `NUM_ROWS` is outside of 1-32
+    InvalidRowCount,
+    /// More than 16 rows per half, but no e pin was given
+    MissingEPin,
+    /// `ROW_LENGTH` doesn't fit the multiplexing, e.g. a `Stripe` needs twice
+    /// the visible width
+    InvalidMultiplexing,
+    /// The pixel data passed to `with_buffer` has no buffers
+    MissingBuffer,
+}
+
+/// A pixel outside of the drawing area was drawn, see `set_strict_bounds`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfBounds {
+    pub point: embedded_graphics::geometry::Point,
+}
+
+/// Clockwise rotation of the drawing area
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+/// Order of the color channels on the r, g and b pins of a panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorOrder {
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+}
+
+impl ColorOrder {
+    /// The channel (0 red, 1 green, 2 blue) driven by the r, g and b pin
+    fn channels(self) -> [usize; 3] {
+        match self {
+            ColorOrder::Rgb => [0, 1, 2],
+            ColorOrder::Rbg => [0, 2, 1],
+            ColorOrder::Grb => [1, 0, 2],
+            ColorOrder::Gbr => [1, 2, 0],
+            ColorOrder::Brg => [2, 0, 1],
+            ColorOrder::Bgr => [2, 1, 0],
+        }
+    }
+}
+
+/// How drawn pixels are combined with the buffer content
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blend {
+    /// Overwrite the pixels
+    Replace,
+    /// Mix with the existing pixels, 255 is fully opaque
+    Alpha(u8),
+    /// Add to the existing pixels, saturating at full brightness
+    Add,
+}
+
+/// How the color channels of the panels are used
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanelColorMode {
+    /// Full color
+    Color,
+    /// Drawn colors are converted to their luminance, e.g. for white-only panels
+    Luminance,
+    /// Three monochrome panels connected to the red, green and blue data pins,
+    /// drawn to side by side in that order, tripling the width
+    ///
+    /// Drawn colors are converted to their luminance.
+    Segments,
+}
+
+/// Offsets of the pwm cycles between parts of the display, for `set_pwm_phases`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PwmPhases {
+    /// All pixels go through the pwm cycles together
+    Aligned,
+    /// The lower half runs half a frame behind the upper one
+    Halves,
+    /// The halves of each chained panel of the given width run evenly spread
+    /// behind each other
+    Panels(usize),
+}
+
+/// Brightness of the chained panels, for `set_panel_calibration`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PanelCalibration {
+    /// Columns of the buffer per panel, the panels follow each other
+    pub width: usize,
+    /// Brightness of every panel, 255 is full brightness
+    ///
+    /// Panels without an entry keep full brightness.
+    pub scales: &'static [u8],
+}
+
+/// Pixel formats of raw images for `blit`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// Three bytes per pixel, red first
+    Rgb888,
+    /// Two bytes per pixel, big endian like `ImageRawBE`
+    Rgb565,
+}
+
+/// Pixel storage, as it's shifted out
+///
+/// `buffer[row][column]` contains the red, green and blue value of the pixel at
+/// `(column, row)` in the first three elements and of the pixel at
+/// `(column, row + NUM_ROWS)` in the last three, before color correction.
+//                      r1, g1, b1, r2, g2, b2, column, row
+pub type Buffer<const NUM_ROWS: usize, const ROW_LENGTH: usize> =
+    [[(u8, u8, u8, u8, u8, u8); ROW_LENGTH]; NUM_ROWS];
+
+/// Pixel data that can be output to the display
+pub trait Frame<const NUM_ROWS: usize, const ROW_LENGTH: usize> {
+    /// Red, green and blue values of the pixels at `(column, row)` and
+    /// `(column, row + NUM_ROWS)`, before color correction
+    fn pixels(&self, row: usize, column: usize) -> (u8, u8, u8, u8, u8, u8);
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Frame<NUM_ROWS, ROW_LENGTH>
+    for Buffer<NUM_ROWS, ROW_LENGTH>
+{
+    fn pixels(&self, row: usize, column: usize) -> (u8, u8, u8, u8, u8, u8) {
+        self[row][column]
+    }
+}
+
+// This table remaps linear input values
+// (the numbers we’d like to use; e.g. 127 = half brightness)
+// to nonlinear gamma-corrected output values
+// (numbers producing the desired effect on the LED;
+// e.g. 36 = half brightness).
+/// Default color correction table
+pub static GAMMA8: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
+    5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
+    14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
+    27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
+    47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
+    105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
+    138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
+    223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+/// Color correction table that leaves the values unchanged
+pub static LINEAR: [u8; 256] = linear();
+
+const fn linear() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+/// Color correction table following the CIE 1931 lightness curve
+///
+/// Perceptually more even than a power law, especially for dark values, which
+/// stay visible at low brightness instead of being rounded to 0.
+pub static CIE1931: [u8; 256] = cie1931();
+
+const fn cie1931() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let value = i as u64;
+        // The lightness is `100 * value / 255`, its luminance is linear up to
+        // a lightness of 8 and cubic above
+        table[i] = if value * 100 <= 8 * 255 {
+            (value * 1000 + 9033 / 2) / 9033
+        } else {
+            let base = value * 100 + 16 * 255;
+            let scale = 116 * 255;
+            (255 * base * base * base + scale * scale * scale / 2) / (scale * scale * scale)
+        } as u8;
+        i += 1;
+    }
+    table
+}
+
+/// Generate a color correction table for a gamma of `exponent / 10`
+///
+/// Evaluated at compile time when used for a static, e.g.
+/// `static GAMMA22: [u8; 256] = gamma(22);`. LEDs usually look right with 1.8
+/// to 2.8, `GAMMA8` is the table for 2.8.
+pub const fn gamma(exponent: u8) -> [u8; 256] {
+    /// `(numerator / denominator)^power` as `mantissa * 2^exponent`, with the
+    /// highest bit of the 64 bit mantissa set, so tiny values keep their precision
+    const fn pow(numerator: u128, denominator: u128, power: u8) -> (u128, i32) {
+        const fn normalize(mantissa: u128, exponent: i32) -> (u128, i32) {
+            let shift = 64 - mantissa.leading_zeros() as i32;
+            if shift < 0 {
+                (mantissa << -shift, exponent + shift)
+            } else {
+                (mantissa >> shift, exponent + shift)
+            }
+        }
+        let base = normalize((numerator << 64) / denominator, -64);
+        let mut result = (1 << 63, -63);
+        let mut i = 0;
+        while i < power {
+            result = normalize(result.0 * base.0, result.1 + base.1);
+            i += 1;
+        }
+        result
+    }
+    let mut table = [0; 256];
+    let mut i = 1;
+    while i < 256 {
+        // Find the largest output o rounding to at most i^(exponent / 10), by
+        // comparing ((o - 0.5) / 255)^10 with (i / 255)^exponent
+        let target = pow(i as u128, 255, exponent);
+        let mut output = 0;
+        let mut bit = 128;
+        while bit > 0 {
+            let (mantissa, exponent) = pow(2 * (output + bit) as u128 - 1, 510, 10);
+            if exponent < target.1 || (exponent == target.1 && mantissa <= target.0) {
+                output += bit;
+            }
+            bit >>= 1;
+        }
+        table[i] = output as u8;
+        i += 1;
+    }
+    table
+}
+
 /// A trait, so that it's easier to reason about the pins
 /// Implemented for a tuple `(r1, g1, b1, r2, g2, b2, a, b, c, d, clk, lat, oe)`
 /// with every element implementing `OutputPin`
-/// f pin is needed for 64x64 matrix support
+/// or `(r1, g1, b1, r2, g2, b2, a, b, c, d, e, clk, lat, oe)` for panels using the e pin
+/// e pin is needed for 64x64 matrix support
 pub trait Outputs {
     type Error;
     type R1: OutputPin<Error = Self::Error>;
@@ -48,8 +439,7 @@ pub trait Outputs {
     type B: OutputPin<Error = Self::Error>;
     type C: OutputPin<Error = Self::Error>;
     type D: OutputPin<Error = Self::Error>;
-    #[cfg(feature = "size-64x64")]
-    type F: OutputPin<Error = Self::Error>;
+    type E: OutputPin<Error = Self::Error>;
     type CLK: OutputPin<Error = Self::Error>;
     type LAT: OutputPin<Error = Self::Error>;
     type OE: OutputPin<Error = Self::Error>;
@@ -63,33 +453,32 @@ pub trait Outputs {
     fn b(&mut self) -> &mut Self::B;
     fn c(&mut self) -> &mut Self::C;
     fn d(&mut self) -> &mut Self::D;
-    #[cfg(feature = "size-64x64")]
-    fn f(&mut self) -> &mut Self::F;
+    /// `None` if the e pin isn't connected
+    fn e(&mut self) -> Option<&mut Self::E>;
     fn clk(&mut self) -> &mut Self::CLK;
     fn lat(&mut self) -> &mut Self::LAT;
     fn oe(&mut self) -> &mut Self::OE;
 }
 
-#[cfg(feature = "size-64x64")]
 impl<
-        E,
-        R1: OutputPin<Error = E>,
-        G1: OutputPin<Error = E>,
-        B1: OutputPin<Error = E>,
-        R2: OutputPin<Error = E>,
-        G2: OutputPin<Error = E>,
-        B2: OutputPin<Error = E>,
-        A: OutputPin<Error = E>,
-        B: OutputPin<Error = E>,
-        C: OutputPin<Error = E>,
-        D: OutputPin<Error = E>,
-        F: OutputPin<Error = E>,
-        CLK: OutputPin<Error = E>,
-        LAT: OutputPin<Error = E>,
-        OE: OutputPin<Error = E>,
-    > Outputs for (R1, G1, B1, R2, G2, B2, A, B, C, D, F, CLK, LAT, OE)
+        PinError,
+        R1: OutputPin<Error = PinError>,
+        G1: OutputPin<Error = PinError>,
+        B1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        G2: OutputPin<Error = PinError>,
+        B2: OutputPin<Error = PinError>,
+        A: OutputPin<Error = PinError>,
+        B: OutputPin<Error = PinError>,
+        C: OutputPin<Error = PinError>,
+        D: OutputPin<Error = PinError>,
+        E: OutputPin<Error = PinError>,
+        CLK: OutputPin<Error = PinError>,
+        LAT: OutputPin<Error = PinError>,
+        OE: OutputPin<Error = PinError>,
+    > Outputs for (R1, G1, B1, R2, G2, B2, A, B, C, D, E, CLK, LAT, OE)
 {
-    type Error = E;
+    type Error = PinError;
     type R1 = R1;
     type G1 = G1;
     type B1 = B1;
@@ -100,7 +489,7 @@ impl<
     type B = B;
     type C = C;
     type D = D;
-    type F = F;
+    type E = E;
     type CLK = CLK;
     type LAT = LAT;
     type OE = OE;
@@ -134,8 +523,8 @@ impl<
     fn d(&mut self) -> &mut D {
         &mut self.9
     }
-    fn f(&mut self) -> &mut F {
-        &mut self.10
+    fn e(&mut self) -> Option<&mut E> {
+        Some(&mut self.10)
     }
     fn clk(&mut self) -> &mut CLK {
         &mut self.11
@@ -148,25 +537,24 @@ impl<
     }
 }
 
-#[cfg(not(feature = "size-64x64"))]
 impl<
-        E,
-        R1: OutputPin<Error = E>,
-        G1: OutputPin<Error = E>,
-        B1: OutputPin<Error = E>,
-        R2: OutputPin<Error = E>,
-        G2: OutputPin<Error = E>,
-        B2: OutputPin<Error = E>,
-        A: OutputPin<Error = E>,
-        B: OutputPin<Error = E>,
-        C: OutputPin<Error = E>,
-        D: OutputPin<Error = E>,
-        CLK: OutputPin<Error = E>,
-        LAT: OutputPin<Error = E>,
-        OE: OutputPin<Error = E>,
+        PinError,
+        R1: OutputPin<Error = PinError>,
+        G1: OutputPin<Error = PinError>,
+        B1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        G2: OutputPin<Error = PinError>,
+        B2: OutputPin<Error = PinError>,
+        A: OutputPin<Error = PinError>,
+        B: OutputPin<Error = PinError>,
+        C: OutputPin<Error = PinError>,
+        D: OutputPin<Error = PinError>,
+        CLK: OutputPin<Error = PinError>,
+        LAT: OutputPin<Error = PinError>,
+        OE: OutputPin<Error = PinError>,
     > Outputs for (R1, G1, B1, R2, G2, B2, A, B, C, D, CLK, LAT, OE)
 {
-    type Error = E;
+    type Error = PinError;
     type R1 = R1;
     type G1 = G1;
     type B1 = B1;
@@ -177,6 +565,8 @@ impl<
     type B = B;
     type C = C;
     type D = D;
+    // Never handed out, `e` always returns `None`
+    type E = D;
     type CLK = CLK;
     type LAT = LAT;
     type OE = OE;
@@ -210,6 +600,9 @@ impl<
     fn d(&mut self) -> &mut D {
         &mut self.9
     }
+    fn e(&mut self) -> Option<&mut D> {
+        None
+    }
     fn clk(&mut self) -> &mut CLK {
         &mut self.10
     }
@@ -221,7 +614,9 @@ impl<
     }
 }
 
-impl<PINS: Outputs> Hub75<PINS> {
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR>
+    Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR>
+{
     /// Create a new hub instance
     ///
     /// Takes an implementation of the Outputs trait,
@@ -233,113 +628,264 @@ impl<PINS: Outputs> Hub75<PINS> {
     /// but each extra bit doubles the time `output` will take. This might lead to noticable flicker.
     ///
     /// 3-4 bits are usually a good choice.
+    ///
+    /// Panels with more than 16 rows per half need the e pin to be connected.
+    ///
+    /// Panics if the configuration is invalid, see `try_new`.
     pub fn new(pins: PINS, brightness_bits: u8) -> Self {
-        assert!(brightness_bits < 9 && brightness_bits > 0);
-        let data = [[(0, 0, 0, 0, 0, 0); 64]; NUM_ROWS];
-        let brightness_step = 1 << (8 - brightness_bits);
-        let brightness_count = ((1 << brightness_bits as u16) - 1) as u8;
-        Self {
-            data,
-            brightness_step,
-            brightness_count,
-            pins,
+        match Self::try_new(pins, brightness_bits) {
+            Ok(hub) => hub,
+            Err(e) => panic!("Invalid configuration: {:?}", e),
         }
     }
 
-    /// Output the buffer to the display
+    /// Create a new hub instance, like `new`, but return an error instead of
+    /// panicking if the configuration is invalid
+    pub fn try_new(pins: PINS, brightness_bits: u8) -> Result<Self, ConfigError> {
+        let data = [[[(0, 0, 0, 0, 0, 0); ROW_LENGTH]; NUM_ROWS]; 1];
+        Self::try_with_buffer(pins, brightness_bits, data)
+    }
+
+    /// Create a new hub instance with a front and a back buffer
     ///
-    /// Takes some time and should be called quite often, otherwise the output
-    /// will flicker
-    pub fn output<DELAY: DelayUs<u8>>(&mut self, delay: &mut DELAY) -> Result<(), PINS::Error> {
-        // Enable the output
-        // The previous last row will continue to display
-        self.pins.oe().set_low()?;
-        // PWM cycle
-        for mut brightness in 0..self.brightness_count {
-            brightness = (brightness + 1).saturating_mul(self.brightness_step);
-            for (count, row) in self.data.iter().enumerate() {
-                for element in row.iter() {
-                    if element.0 >= brightness {
-                        self.pins.r1().set_high()?;
-                    } else {
-                        self.pins.r1().set_low()?;
-                    }
-                    if element.1 >= brightness {
-                        self.pins.g1().set_high()?;
-                    } else {
-                        self.pins.g1().set_low()?;
-                    }
-                    if element.2 >= brightness {
-                        self.pins.b1().set_high()?;
-                    } else {
-                        self.pins.b1().set_low()?;
-                    }
-                    if element.3 >= brightness {
-                        self.pins.r2().set_high()?;
-                    } else {
-                        self.pins.r2().set_low()?;
-                    }
-                    if element.4 >= brightness {
-                        self.pins.g2().set_high()?;
-                    } else {
-                        self.pins.g2().set_low()?;
-                    }
-                    if element.5 >= brightness {
-                        self.pins.b2().set_high()?;
-                    } else {
-                        self.pins.b2().set_low()?;
-                    }
-                    self.pins.clk().set_high()?;
-                    self.pins.clk().set_low()?;
-                }
-                self.pins.oe().set_high()?;
-                // Prevents ghosting, no idea why
-                delay.delay_us(2);
-                self.pins.lat().set_low()?;
-                delay.delay_us(2);
-                self.pins.lat().set_high()?;
-                // Select row
-                if count & 1 != 0 {
-                    self.pins.a().set_high()?;
-                } else {
-                    self.pins.a().set_low()?;
-                }
-                if count & 2 != 0 {
-                    self.pins.b().set_high()?;
-                } else {
-                    self.pins.b().set_low()?;
-                }
-                if count & 4 != 0 {
-                    self.pins.c().set_high()?;
-                } else {
-                    self.pins.c().set_low()?;
-                }
-                if count & 8 != 0 {
-                    self.pins.d().set_high()?;
-                } else {
-                    self.pins.d().set_low()?;
-                }
-                #[cfg(feature = "size-64x64")]
-                if count & 16 != 0 {
-                    self.pins.f().set_high()?;
-                } else {
-                    self.pins.f().set_low()?;
+    /// Prevents tearing when `output` is called while a frame is only partially
+    /// drawn, at the cost of twice the ram. Drawing goes to the back buffer,
+    /// which is shown after calling `swap_buffers`.
+    ///
+    /// Panics if the configuration is invalid, see `try_new`.
+    pub fn new_double_buffered(
+        pins: PINS,
+        brightness_bits: u8,
+    ) -> DoubleBuffered<PINS, NUM_ROWS, ROW_LENGTH, COLOR> {
+        match Self::try_new_double_buffered(pins, brightness_bits) {
+            Ok(hub) => hub,
+            Err(e) => panic!("Invalid configuration: {:?}", e),
+        }
+    }
+
+    /// Create a new hub instance, like `new_double_buffered`, but return an
+    /// error instead of panicking if the configuration is invalid
+    pub fn try_new_double_buffered(
+        pins: PINS,
+        brightness_bits: u8,
+    ) -> Result<DoubleBuffered<PINS, NUM_ROWS, ROW_LENGTH, COLOR>, ConfigError> {
+        let data = [[[(0, 0, 0, 0, 0, 0); ROW_LENGTH]; NUM_ROWS]; 2];
+        Hub75::try_with_buffer(pins, brightness_bits, data)
+    }
+}
+
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA>
+    Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>
+where
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+{
+    /// Width of the drawing area for straight panels without rotation, including
+    /// all chained panels
+    pub const WIDTH: usize = ROW_LENGTH;
+    /// Height of the drawing area for straight panels without rotation
+    pub const HEIGHT: usize = NUM_ROWS * 2;
+
+    /// Create a new hub instance, like `new`, but with the pixel data stored in
+    /// `data`
+    ///
+    /// `data` is usually a `&'static mut [Buffer<NUM_ROWS, ROW_LENGTH>]`, e.g.
+    /// placed in ccm or external ram with `#[link_section]`. It is cleared, as
+    /// such memory usually isn't initialized. With two buffers, drawing goes
+    /// to a back buffer, see `new_double_buffered`.
+    ///
+    /// Panics if the configuration is invalid, see `try_new`.
+    pub fn with_buffer(pins: PINS, brightness_bits: u8, data: DATA) -> Self {
+        match Self::try_with_buffer(pins, brightness_bits, data) {
+            Ok(hub) => hub,
+            Err(e) => panic!("Invalid configuration: {:?}", e),
+        }
+    }
+
+    /// Create a new hub instance, like `with_buffer`, but return an error
+    /// instead of panicking if the configuration is invalid
+    pub fn try_with_buffer(
+        mut pins: PINS,
+        brightness_bits: u8,
+        mut data: DATA,
+    ) -> Result<Self, ConfigError> {
+        if NUM_ROWS == 0 || NUM_ROWS > 32 {
+            return Err(ConfigError::InvalidRowCount);
+        }
+        if NUM_ROWS > 16 && pins.e().is_none() {
+            return Err(ConfigError::MissingEPin);
+        }
+        if data.borrow().is_empty() {
+            return Err(ConfigError::MissingBuffer);
+        }
+        for buffer in data.borrow_mut().iter_mut() {
+            for row in buffer.iter_mut() {
+                for e in row.iter_mut() {
+                    *e = (0, 0, 0, 0, 0, 0);
                 }
-                delay.delay_us(2);
-                self.pins.oe().set_low()?;
             }
         }
-        // Disable the output
-        // Prevents one row from being much brighter than the others
-        self.pins.oe().set_high()?;
-        Ok(())
+        let mut driver = Driver {
+            brightness_step: 0,
+            brightness_count: 0,
+            color_correction: [&GAMMA8; 3],
+            global_brightness: 255,
+            current_limit: None,
+            limited_brightness: 255,
+            estimated_current: 0,
+            step_peak: 0,
+            white_balance: [255; 3],
+            color_order: [ColorOrder::Rgb; 2],
+            addressing: &Direct,
+            filter: None,
+            column_map: None,
+            latch_blanking: 2,
+            oe_inverted: false,
+            lat_inverted: false,
+            dithering: false,
+            interleaved: false,
+            phases: PwmPhases::Aligned,
+            calibration: None,
+            frame_count: 0,
+            off: false,
+            step_row: 0,
+            step_brightness: 0,
+            pins,
+        };
+        driver.set_brightness_bits(brightness_bits)?;
+        Ok(Self {
+            data,
+            front: 0,
+            driver,
+            layout: Layout {
+                multiplexing: &Straight,
+                rotation: Rotation::Deg0,
+                mirror: (false, false),
+                blend: Blend::Replace,
+                color_mode: PanelColorMode::Color,
+            },
+            strict_bounds: false,
+            color: PhantomData,
+        })
     }
+
+    /// Configure panels using the FM6126A driver chip
+    ///
+    /// These stay dark until their configuration registers are written, so call
+    /// this once before the first `output`.
+    pub fn init_fm6126a(&mut self) -> Result<(), PINS::Error> {
+        self.driver.init_fm6126a()
+    }
+
+    /// Output the buffer to the display
+    ///
+    /// Takes some time and should be called quite often, otherwise the output
+    /// will flicker
+    pub fn output<DELAY: DelayNs>(&mut self, delay: &mut DELAY) -> Result<(), PINS::Error> {
+        self.driver
+            .output(&self.data.borrow()[self.front], delay, || {})
+    }
+
+    /// Output the buffer to the display, calling `yield_now` after every row
+    ///
+    /// For work that can't wait for a whole frame, e.g. feeding a watchdog or
+    /// polling a uart. The row stays lit while `yield_now` runs, so it should
+    /// be short and take about the same time on every call, otherwise the rows
+    /// are lit unevenly.
+    pub fn output_yielding<DELAY, Y>(
+        &mut self,
+        delay: &mut DELAY,
+        yield_now: Y,
+    ) -> Result<(), PINS::Error>
+    where
+        DELAY: DelayNs,
+        Y: FnMut(),
+    {
+        self.driver
+            .output(&self.data.borrow()[self.front], delay, yield_now)
+    }
+
+    /// Output another frame instead of the buffer to the display
+    ///
+    /// Works like `output`, e.g. for showing a `Framebuffer` or `PaletteBuffer`.
+    pub fn output_frame<F, DELAY>(
+        &mut self,
+        frame: &F,
+        delay: &mut DELAY,
+    ) -> Result<(), PINS::Error>
+    where
+        F: Frame<NUM_ROWS, ROW_LENGTH>,
+        DELAY: DelayNs,
+    {
+        self.driver.output(frame, delay, || {})
+    }
+
+    /// Output the buffer to the display, yielding during the delays
+    ///
+    /// Works like `output`, but lets other tasks run while waiting, e.g. in an
+    /// embassy executor.
+    #[cfg(feature = "async")]
+    pub async fn output_async<DELAY: embedded_hal_async::delay::DelayNs>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), PINS::Error> {
+        self.driver
+            .output_async(&self.data.borrow()[self.front], delay)
+            .await
+    }
+
+    /// Output a single row of one pwm cycle
+    ///
+    /// This allows driving the display from a timer interrupt instead of
+    /// blocking in `output`. The row stays lit until the next call, so the calls
+    /// should be evenly spaced, every row and pwm cycle takes the same time.
+    /// A full frame takes `NUM_ROWS * (2^brightness_bits - 1)` calls.
+    ///
+    /// Every call does a bounded amount of work, see `step_cost`. With a
+    /// current limit, the estimate is spread over the calls, so the limit
+    /// applies from the frame after the one it was estimated for.
+    ///
+    /// Returns `true` when the last row of a frame was output
+    pub fn output_step<DELAY: DelayNs>(&mut self, delay: &mut DELAY) -> Result<bool, PINS::Error> {
+        self.driver
+            .output_step(&self.data.borrow()[self.front], delay)
+    }
+
+    /// Upper bound of the work done by one `output_step` call, e.g. for
+    /// budgeting it in a real time scheduler
+    ///
+    /// Depends on the addressing and latch blanking, so it should be checked
+    /// again after changing them.
+    pub fn step_cost(&self) -> StepCost {
+        self.driver.step_cost()
+    }
+
+    /// Turn the display off, keeping the buffer
+    ///
+    /// Disables the output and sets the other pins low, e.g. before sleeping
+    /// or when the refresh loop pauses, which would leave the last row lit.
+    /// The output functions return without doing anything until `display_on`
+    /// is called.
+    pub fn display_off(&mut self) -> Result<(), PINS::Error> {
+        self.driver.display_off()
+    }
+
+    /// Turn the display on again after `display_off`
+    ///
+    /// Nothing is shown until the next `output`.
+    pub fn display_on(&mut self) {
+        self.driver.off = false;
+    }
+
+    pub fn is_display_on(&self) -> bool {
+        !self.driver.off
+    }
+
     /// Clear the output
     ///
     /// It's a bit faster than using the embedded_graphics interface
     /// to do the same
     pub fn clear(&mut self) {
-        for row in self.data.iter_mut() {
+        for row in self.draw_buffer().iter_mut() {
             for e in row.iter_mut() {
                 e.0 = 0;
                 e.1 = 0;
@@ -350,59 +896,1354 @@ impl<PINS: Outputs> Hub75<PINS> {
             }
         }
     }
+
+    /// Number of words needed for `render_bitplanes`
+    pub fn bitplanes_len(&self) -> usize {
+        self.brightness_bits() as usize * NUM_ROWS * ROW_LENGTH
+    }
+
+    /// Prerender the buffer into a word stream, e.g. for output with dma
+    ///
+    /// Writes one word per clock pulse, with r1, g1, b1, r2, g2, b2 in bit 0 to 5.
+    /// The words are ordered by bitplane (least significant bit first), then row,
+    /// then column. Bitplane `n` has to be shown `2^n` times as long as bitplane 0
+    /// to reproduce the brightness `output` produces.
+    ///
+    /// `out` has to be at least `bitplanes_len` words long.
+    pub fn render_bitplanes(&self, out: &mut [u16]) {
+        assert!(out.len() >= self.bitplanes_len());
+        self.driver
+            .render_bitplanes(&self.data.borrow()[self.front], out);
+    }
+
+    /// Prerender a single row of one bitplane, like `render_bitplanes`
+    ///
+    /// Allows rendering the next row while the current one is output with dma,
+    /// needing only two rows of memory instead of all bitplanes.
+    ///
+    /// `out` has to be at least `ROW_LENGTH` words long.
+    pub fn render_row(&self, row: usize, plane: u8, out: &mut [u16]) {
+        assert!(row < NUM_ROWS && plane < self.brightness_bits());
+        self.driver
+            .render_row(&self.data.borrow()[self.front], row, plane, out);
+    }
+
+    /// Number of brightness bits for each color
+    pub fn brightness_bits(&self) -> u8 {
+        self.driver.brightness_bits()
+    }
+
+    /// Change the number of brightness bits for each color (1-8)
+    ///
+    /// Allows trading color depth for refresh rate, e.g. fewer bits during fast
+    /// animations. The drawn content is kept. Restarts the frame of `output_step`.
+    pub fn set_brightness_bits(&mut self, brightness_bits: u8) -> Result<(), ConfigError> {
+        self.driver.set_brightness_bits(brightness_bits)
+    }
+
+    /// Set the color correction applied to the red, green and blue values
+    ///
+    /// Each table maps the drawn value of its channel to the output brightness.
+    /// By default, `GAMMA8` is used for all channels, `LINEAR` disables
+    /// the correction, `CIE1931` keeps more dark values and `gamma` generates
+    /// tables for other exponents. The tables are applied during output, so
+    /// this also changes the already drawn content.
+    pub fn set_color_correction(
+        &mut self,
+        r: &'static [u8; 256],
+        g: &'static [u8; 256],
+        b: &'static [u8; 256],
+    ) {
+        self.driver.color_correction = [r, g, b];
+    }
+
+    /// Set the brightness of the whole display
+    ///
+    /// All values are scaled by `brightness / 255` during output, so the
+    /// drawn content isn't changed. Defaults to 255.
+    ///
+    /// The scaling happens after color correction, in linear light, so dimmed
+    /// colors keep their hue. Very dark channels are rounded off, enabling
+    /// dithering or using `PwmOe` keeps them.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.driver.global_brightness = brightness;
+    }
+
+    /// Set the order of the color channels for the upper and lower half
+    ///
+    /// Some panels are wired with e.g. green on the r pins. Defaults to
+    /// `ColorOrder::Rgb` for both halves.
+    pub fn set_color_order(&mut self, upper: ColorOrder, lower: ColorOrder) {
+        self.driver.color_order = [upper, lower];
+    }
+
+    /// Spread the time each pixel is lit over the whole frame
+    ///
+    /// By default, a pixel is lit in one piece at the start of every frame,
+    /// which flickers on cameras and at low refresh rates. Interleaving the pwm
+    /// cycles splits this into several shorter pieces, like the scrambled pwm of
+    /// some driver chips, without changing the brightness or output time.
+    pub fn set_interleaved_pwm(&mut self, enabled: bool) {
+        self.driver.interleaved = enabled;
+    }
+
+    /// Offset the pwm cycles of parts of the display against each other
+    ///
+    /// By default, all pixels are lit in the same pwm cycles, so bright
+    /// content draws its current in bursts, which can make the supply whine.
+    /// Offsetting the halves or chained panels spreads the current over the
+    /// frame, without changing the brightness or output time.
+    pub fn set_pwm_phases(&mut self, phases: PwmPhases) {
+        self.driver.phases = phases;
+    }
+
+    /// Limit the brightness to stay within the current the supply can provide
+    ///
+    /// The peak current of every frame is estimated before it's output, and
+    /// the whole display is dimmed if it would exceed `budget_ma`.
+    /// `None` disables the limit, which is the default.
+    pub fn set_current_limit(&mut self, limit: Option<CurrentLimit>) {
+        self.driver.current_limit = limit;
+    }
+
+    /// Estimated peak current of the last frame before dimming, in mA
+    ///
+    /// Only updated while a current limit is set.
+    pub fn estimated_current(&self) -> u32 {
+        self.driver.estimated_current
+    }
+
+    /// Set the white balance of the display
+    ///
+    /// The red, green and blue values are scaled by `r / 255`, `g / 255` and
+    /// `b / 255` during output, after color correction. This allows matching
+    /// panels from different batches. Defaults to 255 for every channel.
+    pub fn set_white_balance(&mut self, r: u8, g: u8, b: u8) {
+        self.driver.white_balance = [r, g, b];
+    }
+
+    /// Set the brightness of every chained panel
+    ///
+    /// Scales the panels during output on top of the global brightness and
+    /// white balance, e.g. to match the panels of a video wall coming from
+    /// different batches. With a `TileLayout`, the panels are counted in chain
+    /// order. `None` lights every panel at full brightness, the default.
+    pub fn set_panel_calibration(&mut self, calibration: Option<PanelCalibration>) {
+        self.driver.calibration = calibration;
+    }
+
+    /// Enable temporal dithering
+    ///
+    /// Adds two bits of color depth without making `output` slower, by turning
+    /// pixels between two brightness levels on in some of four consecutive frames.
+    /// Needs a high enough refresh rate, otherwise the dithered pixels flicker.
+    /// Not applied by `render_bitplanes`.
+    pub fn set_dithering(&mut self, enabled: bool) {
+        self.driver.dithering = enabled;
+    }
+
+    /// Set the time in µs the output is turned off while latching a row
+    ///
+    /// Longer times reduce ghosting of the previous row, but make the display
+    /// darker and take longer to output. Defaults to 2, some panels
+    /// need more, others work with 0.
+    pub fn set_latch_blanking(&mut self, us: u8) {
+        self.driver.latch_blanking = us;
+    }
+
+    /// Drive oe high instead of low to enable the output
+    ///
+    /// For boards inverting oe, e.g. with their level shifters. A `PwmOe`
+    /// should be inverted with the polarity of its pwm channel instead.
+    pub fn set_oe_inverted(&mut self, inverted: bool) {
+        self.driver.oe_inverted = inverted;
+    }
+
+    /// Pulse lat low instead of high to latch a row
+    ///
+    /// For boards inverting lat, e.g. with their level shifters.
+    pub fn set_latch_inverted(&mut self, inverted: bool) {
+        self.driver.lat_inverted = inverted;
+    }
+
+    /// Shift out the columns of the buffer in a different order
+    ///
+    /// For panels scrambling their columns, e.g. in reversed groups, which
+    /// aren't covered by `set_multiplexing`. The `n`th column shifted out is
+    /// column `map[n]` of the buffer. Positions missing from the map or
+    /// pointing outside of the buffer are shifted out unchanged. Applied during
+    /// output, so this also changes the already drawn content. `None` removes
+    /// the map, which is the default.
+    pub fn set_column_map(&mut self, map: Option<&'static [usize]>) {
+        self.driver.column_map = map;
+    }
+
+    /// Pass every pixel through `filter` during output
+    ///
+    /// The filter is called for every pixel in every pwm cycle, so it should be
+    /// cheap. The result isn't stored, so the drawn content stays unchanged.
+    /// State shared with the filter has to be `Sync`, e.g. atomics, so the
+    /// display can still be moved to another core. `None` removes the filter,
+    /// which is the default.
+    pub fn set_output_filter(&mut self, filter: Option<&'static (dyn OutputFilter + Sync)>) {
+        self.driver.filter = filter;
+    }
+
+    /// Set how the panel selects rows
+    ///
+    /// Defaults to `Direct`.
+    pub fn set_addressing(&mut self, addressing: &'static (dyn Addressing + Sync)) {
+        self.driver.addressing = addressing;
+    }
+
+    /// Set the order the panel expects its pixels in
+    ///
+    /// Defaults to `Straight`, only affects what's drawn afterwards. The size
+    /// of the drawing area follows the multiplexing, see `visible_size`. Fails
+    /// without changing the multiplexing if it doesn't fit `ROW_LENGTH`.
+    pub fn set_multiplexing(
+        &mut self,
+        multiplexing: &'static (dyn Multiplexing + Sync),
+    ) -> Result<(), ConfigError> {
+        if !multiplexing.fits(ROW_LENGTH, 2 * NUM_ROWS) {
+            return Err(ConfigError::InvalidMultiplexing);
+        }
+        self.layout.multiplexing = multiplexing;
+        Ok(())
+    }
+
+    /// Use `Stripe` multiplexing, checking `ROW_LENGTH` while building
+    ///
+    /// Works like `set_multiplexing(&Stripe { .. })`, but fails to compile
+    /// instead of returning an error if `ROW_LENGTH` isn't a multiple of
+    /// `2 * BLOCK_WIDTH`, e.g. `display.set_stripe::<8, false>()` for 1/4 scan
+    /// 32x16 panels with `Hub75<_, 4, 64>`.
+    pub fn set_stripe<const BLOCK_WIDTH: usize, const FIRST_ROW_FIRST: bool>(&mut self) {
+        const {
+            assert!(
+                BLOCK_WIDTH > 0 && ROW_LENGTH.is_multiple_of(2 * BLOCK_WIDTH),
+                "the row length of a stripe panel has to be a multiple of 2 * block_width"
+            );
+        }
+        self.layout.multiplexing = &const {
+            Stripe {
+                block_width: BLOCK_WIDTH,
+                first_row_first: FIRST_ROW_FIRST,
+            }
+        };
+    }
+
+    /// Size of the panels in pixels, without the rotation
+    ///
+    /// Differs from `ROW_LENGTH` and `2 * NUM_ROWS` with multiplexing, e.g. a
+    /// `Stripe` panel is half as wide and twice as high as the buffer.
+    pub fn visible_size(&self) -> Size {
+        self.layout.visible_size()
+    }
+
+    /// Rotate the drawing area, e.g. for panels mounted upside down
+    ///
+    /// Only affects what's drawn afterwards. With 90 and 270 degrees, width and
+    /// height of the drawing area are swapped.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.layout.rotation = rotation;
+    }
+
+    /// Mirror the drawing area horizontally and/or vertically
+    ///
+    /// Only affects what's drawn afterwards. The mirroring is applied before
+    /// the rotation.
+    pub fn set_mirroring(&mut self, horizontal: bool, vertical: bool) {
+        self.layout.mirror = (horizontal, vertical);
+    }
+
+    /// Map a point of the drawing area, see `Layout::map_point`
+    fn map_point(&self, x: i32, y: i32) -> Option<(usize, usize, usize)> {
+        self.layout.map_point(x, y)
+    }
+
+    /// Size of the drawing area, after multiplexing and rotation
+    fn drawing_size(&self) -> (usize, usize) {
+        self.layout.drawing_size()
+    }
+
+    /// Set how the color channels of the panels are used
+    ///
+    /// Applies to all following drawing operations. Defaults to
+    /// `PanelColorMode::Color`.
+    pub fn set_color_mode(&mut self, color_mode: PanelColorMode) {
+        self.layout.color_mode = color_mode;
+    }
+
+    /// Set how drawing combines pixels with the current content
+    ///
+    /// Applies to all following drawing operations, e.g. for overlays.
+    /// Defaults to `Blend::Replace`.
+    pub fn set_blend_mode(&mut self, blend: Blend) {
+        self.layout.blend = blend;
+    }
+
+    /// Return `OutOfBounds` when drawing pixels outside of the drawing area
+    ///
+    /// By default, these are silently dropped, which can hide layout bugs, so
+    /// this is meant for development builds, e.g.
+    /// `set_strict_bounds(cfg!(debug_assertions))`. Drawing stops at the first
+    /// of these pixels.
+    pub fn set_strict_bounds(&mut self, strict: bool) {
+        self.strict_bounds = strict;
+    }
+
+    /// Access the pins, e.g. to adjust a `PwmOe`
+    ///
+    /// The pins should be left as they are, otherwise the next `output` might
+    /// be garbled.
+    pub fn pins_mut(&mut self) -> &mut PINS {
+        &mut self.driver.pins
+    }
+
+    /// Show the frame drawn so far
+    ///
+    /// Exchanges the front and back buffer, so `output` will display the
+    /// newly drawn frame, while further drawing goes to the previously
+    /// displayed one. Its content isn't cleared. Does nothing with a single
+    /// buffer, where drawing is shown right away.
+    pub fn swap_buffers(&mut self) {
+        self.front = (self.front + 1) % self.data.borrow().len();
+    }
+
+    /// Direct access to the pixel data, e.g. for copying whole frames
+    ///
+    /// With two buffers, this is the back buffer.
+    pub fn framebuffer_mut(&mut self) -> &mut Buffer<NUM_ROWS, ROW_LENGTH> {
+        self.draw_buffer()
+    }
+
+    /// Read back the pixels being output, e.g. for mirroring them to a preview
+    /// display
+    ///
+    /// The points are in the coordinates of the drawing area, the colors are
+    /// the drawn ones, before color correction.
+    pub fn to_rgb888_iter(&self) -> impl Iterator<Item = Pixel<Rgb888>> + '_ {
+        let buffer = &self.data.borrow()[self.front];
+        self.bounding_box().points().filter_map(move |point| {
+            let (x, y, segment) = self.map_point(point.x, point.y)?;
+            let color = self.stored_color(buffer[y % NUM_ROWS][x], y, segment);
+            Some(Pixel(point, color))
+        })
+    }
+
+    /// Read back the drawn color at `(x, y)`, e.g. for collision checks or
+    /// read-modify-write effects
+    ///
+    /// Uses the same coordinates as drawing, including rotation, mirroring
+    /// and multiplexing. The buffer keeps the colors as drawn and color
+    /// correction is only applied during output, so they come back unchanged.
+    /// With two buffers, this reads the back buffer. Returns
+    /// `None` for points outside of the drawing area.
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<Rgb888> {
+        let (x, y, segment) = self.map_point(x, y)?;
+        let data = self.data.borrow()[self.draw_index()][y % NUM_ROWS][x];
+        Some(self.stored_color(data, y, segment))
+    }
+
+    /// Color of the pixel in row `y` of the buffer element `data`
+    fn stored_color(&self, data: (u8, u8, u8, u8, u8, u8), y: usize, segment: usize) -> Rgb888 {
+        let (r, g, b) = if y >= NUM_ROWS {
+            (data.3, data.4, data.5)
+        } else {
+            (data.0, data.1, data.2)
+        };
+        match self.layout.color_mode {
+            PanelColorMode::Segments => {
+                let value = [r, g, b][segment];
+                Rgb888::new(value, value, value)
+            }
+            _ => Rgb888::new(r, g, b),
+        }
+    }
+
+    /// Move the drawn content by `dx` pixels to the right and `dy` pixels down,
+    /// e.g. for marquees
+    ///
+    /// With `wrap`, content leaving on one side enters again on the opposite
+    /// one, otherwise the uncovered pixels are turned off. Horizontal moves on
+    /// straight panels without rotation or mirroring move whole rows of the
+    /// buffer, everything else is moved pixel by pixel.
+    pub fn scroll(&mut self, dx: i32, dy: i32, wrap: bool) {
+        let (width, height) = self.drawing_size();
+        let straight = self.layout.is_straight();
+        // Moved pixels are copied, not blended
+        let blend = core::mem::replace(&mut self.layout.blend, Blend::Replace);
+        if straight {
+            let off = (0, 0, 0, 0, 0, 0);
+            let shift = (dx.unsigned_abs() as usize).min(ROW_LENGTH);
+            for row in self.draw_buffer().iter_mut() {
+                if wrap {
+                    row.rotate_right(dx.rem_euclid(ROW_LENGTH as i32) as usize);
+                } else if dx >= 0 {
+                    row.copy_within(..ROW_LENGTH - shift, shift);
+                    row[..shift].fill(off);
+                } else {
+                    row.copy_within(shift.., 0);
+                    row[ROW_LENGTH - shift..].fill(off);
+                }
+            }
+        } else {
+            for y in 0..height {
+                self.scroll_line(dx, width, wrap, |x| (x, y));
+            }
+        }
+        for x in 0..width {
+            self.scroll_line(dy, height, wrap, |y| (x, y));
+        }
+        self.layout.blend = blend;
+    }
+
+    /// Move the pixels of a line of the drawing area by `offset`, pixel by pixel
+    ///
+    /// `point` returns the position of the pixel at an index of the line.
+    fn scroll_line(
+        &mut self,
+        offset: i32,
+        length: usize,
+        wrap: bool,
+        point: impl Fn(usize) -> (usize, usize),
+    ) {
+        if length == 0 {
+            return;
+        }
+        let get = |hub: &mut Self, index: usize| {
+            let (x, y) = point(index);
+            let (x, y, segment) = hub.map_point(x as i32, y as i32)?;
+            let data = hub.draw_buffer()[y % NUM_ROWS][x];
+            Some(hub.stored_color(data, y, segment))
+        };
+        let set = |hub: &mut Self, index: usize, color: Option<Rgb888>| {
+            let (x, y) = point(index);
+            if let Some((x, y, segment)) = hub.map_point(x as i32, y as i32) {
+                hub.set_pixel(x, y, segment, color.unwrap_or(Rgb888::BLACK));
+            }
+        };
+        if wrap {
+            // Rotate the line in place, following the cycles of moved pixels
+            let shift = offset.rem_euclid(length as i32) as usize;
+            let (mut a, mut b) = (length, shift);
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            for start in 0..a {
+                let first = get(self, start);
+                let mut index = start;
+                loop {
+                    let source = (index + length - shift) % length;
+                    if source == start {
+                        set(self, index, first);
+                        break;
+                    }
+                    let color = get(self, source);
+                    set(self, index, color);
+                    index = source;
+                }
+            }
+        } else if offset >= 0 {
+            for index in (0..length).rev() {
+                let color = match index.checked_sub(offset as usize) {
+                    Some(source) => get(self, source),
+                    None => None,
+                };
+                set(self, index, color);
+            }
+        } else {
+            for index in 0..length {
+                let source = index + offset.unsigned_abs() as usize;
+                let color = if source < length {
+                    get(self, source)
+                } else {
+                    None
+                };
+                set(self, index, color);
+            }
+        }
+    }
+
+    /// The buffer drawing operations should modify
+    fn draw_buffer(&mut self) -> &mut Buffer<NUM_ROWS, ROW_LENGTH> {
+        let index = self.draw_index();
+        &mut self.data.borrow_mut()[index]
+    }
+
+    /// Index of the buffer drawing operations should modify, the front
+    /// buffer itself with a single buffer
+    fn draw_index(&self) -> usize {
+        (self.front + 1) % self.data.borrow().len()
+    }
+}
+
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize>
+    Driver<PINS, NUM_ROWS, ROW_LENGTH>
+{
+    fn display_off(&mut self) -> Result<(), PINS::Error> {
+        self.set_oe(false)?;
+        self.set_lat(false)?;
+        self.pins.clk().set_low()?;
+        self.pins.r1().set_low()?;
+        self.pins.g1().set_low()?;
+        self.pins.b1().set_low()?;
+        self.pins.r2().set_low()?;
+        self.pins.g2().set_low()?;
+        self.pins.b2().set_low()?;
+        self.off = true;
+        // Start the next frame of `output_step` from the top again
+        self.step_row = 0;
+        self.step_brightness = 0;
+        self.step_peak = 0;
+        Ok(())
+    }
+
+    pub fn init_fm6126a(&mut self) -> Result<(), PINS::Error> {
+        // Taken from https://github.com/hzeller/rpi-rgb-led-matrix
+        // The register is selected by the number of clock pulses the latch is held high
+        const REGISTERS: [(u16, usize); 2] =
+            [(0b0111_1111_1111_1111, 11), (0b0000_0000_0100_0000, 12)];
+        self.set_oe(false)?;
+        self.set_lat(false)?;
+        self.pins.clk().set_low()?;
+        for &(value, latch_clocks) in REGISTERS.iter() {
+            for column in 0..ROW_LENGTH {
+                if value & (1 << (15 - column % 16)) != 0 {
+                    self.pins.r1().set_high()?;
+                    self.pins.g1().set_high()?;
+                    self.pins.b1().set_high()?;
+                    self.pins.r2().set_high()?;
+                    self.pins.g2().set_high()?;
+                    self.pins.b2().set_high()?;
+                } else {
+                    self.pins.r1().set_low()?;
+                    self.pins.g1().set_low()?;
+                    self.pins.b1().set_low()?;
+                    self.pins.r2().set_low()?;
+                    self.pins.g2().set_low()?;
+                    self.pins.b2().set_low()?;
+                }
+                if column + latch_clocks >= ROW_LENGTH {
+                    self.set_lat(true)?;
+                }
+                self.pins.clk().set_high()?;
+                self.pins.clk().set_low()?;
+            }
+            self.set_lat(false)?;
+        }
+        Ok(())
+    }
+
+    fn output<F, DELAY, Y>(
+        &mut self,
+        frame: &F,
+        delay: &mut DELAY,
+        mut yield_now: Y,
+    ) -> Result<(), PINS::Error>
+    where
+        F: Frame<NUM_ROWS, ROW_LENGTH>,
+        DELAY: DelayNs,
+        Y: FnMut(),
+    {
+        if self.off {
+            return Ok(());
+        }
+        self.limit_current(frame);
+        // PWM cycle
+        for pass in 0..self.brightness_count {
+            for count in 0..NUM_ROWS {
+                self.output_row(frame, count, pass, delay)?;
+                yield_now();
+            }
+        }
+        // Every row is lit while the next one is shifted out, so the last row
+        // stays lit for as long while shifting out the first one again
+        self.shift_row(frame, 0, 0)?;
+        // Disable the output
+        // Prevents the last row from being brighter when output isn't called
+        // right away again
+        self.set_oe(false)?;
+        self.frame_count = self.frame_count.wrapping_add(1);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    async fn output_async<F, DELAY>(
+        &mut self,
+        frame: &F,
+        delay: &mut DELAY,
+    ) -> Result<(), PINS::Error>
+    where
+        F: Frame<NUM_ROWS, ROW_LENGTH>,
+        DELAY: embedded_hal_async::delay::DelayNs,
+    {
+        if self.off {
+            return Ok(());
+        }
+        self.limit_current(frame);
+        for pass in 0..self.brightness_count {
+            for count in 0..NUM_ROWS {
+                self.shift_row(frame, count, pass)?;
+                self.set_oe(false)?;
+                delay.delay_us(self.latch_blanking as u32).await;
+                self.set_lat(false)?;
+                delay.delay_us(self.latch_blanking as u32).await;
+                self.set_lat(true)?;
+                self.select_row(count)?;
+                delay.delay_us(self.latch_blanking as u32).await;
+                self.set_oe(true)?;
+            }
+        }
+        self.shift_row(frame, 0, 0)?;
+        self.set_oe(false)?;
+        self.frame_count = self.frame_count.wrapping_add(1);
+        Ok(())
+    }
+
+    fn output_step<F, DELAY>(&mut self, frame: &F, delay: &mut DELAY) -> Result<bool, PINS::Error>
+    where
+        F: Frame<NUM_ROWS, ROW_LENGTH>,
+        DELAY: DelayNs,
+    {
+        if self.off {
+            return Ok(true);
+        }
+        if self.step_brightness == 0 && self.current_limit.is_some() {
+            self.step_peak = self.step_peak.max(self.row_current(frame, self.step_row));
+        }
+        self.output_row(frame, self.step_row, self.step_brightness, delay)?;
+        self.step_row += 1;
+        if self.step_row < NUM_ROWS {
+            return Ok(false);
+        }
+        self.step_row = 0;
+        self.step_brightness += 1;
+        if self.step_brightness < self.brightness_count {
+            return Ok(false);
+        }
+        self.step_brightness = 0;
+        self.apply_current_limit(self.step_peak);
+        self.step_peak = 0;
+        self.frame_count = self.frame_count.wrapping_add(1);
+        Ok(true)
+    }
+
+    /// Shift out one row of the pwm cycle `pass`, latch it and select it
+    fn output_row<F: Frame<NUM_ROWS, ROW_LENGTH>, DELAY: DelayNs>(
+        &mut self,
+        frame: &F,
+        count: usize,
+        pass: u8,
+        delay: &mut DELAY,
+    ) -> Result<(), PINS::Error> {
+        self.shift_row(frame, count, pass)?;
+        self.set_oe(false)?;
+        // Prevents ghosting, the row drivers need some time to switch
+        delay.delay_us(self.latch_blanking as u32);
+        self.set_lat(false)?;
+        delay.delay_us(self.latch_blanking as u32);
+        self.set_lat(true)?;
+        self.select_row(count)?;
+        delay.delay_us(self.latch_blanking as u32);
+        self.set_oe(true)?;
+        Ok(())
+    }
+
+    /// Enable or disable the output, depending on the oe polarity
+    fn set_oe(&mut self, enabled: bool) -> Result<(), PINS::Error> {
+        if enabled == self.oe_inverted {
+            self.pins.oe().set_high()
+        } else {
+            self.pins.oe().set_low()
+        }
+    }
+
+    /// Set lat to its active or idle level, depending on its polarity
+    fn set_lat(&mut self, active: bool) -> Result<(), PINS::Error> {
+        if active == self.lat_inverted {
+            self.pins.lat().set_low()
+        } else {
+            self.pins.lat().set_high()
+        }
+    }
+
+    /// Shift out one row of the pwm cycle `pass`, without latching it
+    fn shift_row<F: Frame<NUM_ROWS, ROW_LENGTH>>(
+        &mut self,
+        frame: &F,
+        count: usize,
+        pass: u8,
+    ) -> Result<(), PINS::Error> {
+        let panel_width = match self.phases {
+            PwmPhases::Panels(width) => width.max(1),
+            _ => ROW_LENGTH,
+        };
+        // Thresholds of the upper and lower half, only changing between panels
+        let mut panel = (usize::MAX, 0);
+        let mut thresholds = [[[0; 4]; 3]; 2];
+        let correction = self.color_correction;
+        let channels = self.pin_channels();
+        for column in 0..ROW_LENGTH {
+            let current = (column / panel_width, self.panel_scale(column));
+            if current != panel {
+                panel = current;
+                let (index, scale) = panel;
+                thresholds = [
+                    self.thresholds(self.phase_pass(pass, index, 0), scale),
+                    self.thresholds(self.phase_pass(pass, index, 1), scale),
+                ];
+            }
+            let element = self.pixels(frame, count, column);
+            let pattern = (self.frame_count as usize + count + column) & 3;
+            let values = [
+                element.0, element.1, element.2, element.3, element.4, element.5,
+            ];
+            let on = |pin: usize| {
+                let channel = channels[pin];
+                let value = values[pin / 3 * 3 + channel];
+                correction[channel][value as usize] as u16 >= thresholds[pin / 3][channel][pattern]
+            };
+            if on(0) {
+                self.pins.r1().set_high()?;
+            } else {
+                self.pins.r1().set_low()?;
+            }
+            if on(1) {
+                self.pins.g1().set_high()?;
+            } else {
+                self.pins.g1().set_low()?;
+            }
+            if on(2) {
+                self.pins.b1().set_high()?;
+            } else {
+                self.pins.b1().set_low()?;
+            }
+            if on(3) {
+                self.pins.r2().set_high()?;
+            } else {
+                self.pins.r2().set_low()?;
+            }
+            if on(4) {
+                self.pins.g2().set_high()?;
+            } else {
+                self.pins.g2().set_low()?;
+            }
+            if on(5) {
+                self.pins.b2().set_high()?;
+            } else {
+                self.pins.b2().set_low()?;
+            }
+            self.pins.clk().set_high()?;
+            self.pins.clk().set_low()?;
+        }
+        Ok(())
+    }
+
+    /// The pwm cycle shown in `pass` by one half of a panel, see `PwmPhases`
+    fn phase_pass(&self, pass: u8, panel: usize, half: usize) -> u8 {
+        let count = self.brightness_count as usize;
+        let offset = match self.phases {
+            PwmPhases::Aligned => 0,
+            PwmPhases::Halves => half * count / 2,
+            PwmPhases::Panels(width) => {
+                let parts = 2 * (ROW_LENGTH / width.max(1)).max(1);
+                (2 * panel + half) * count / parts
+            }
+        };
+        ((pass as usize + offset) % count) as u8
+    }
+
+    /// Thresholds of the pwm cycle `pass` for every channel and dithering
+    /// pattern, pixels with at least these values are turned on
+    ///
+    /// The panel is lit with `panel_scale / 255` of the brightness.
+    fn thresholds(&self, pass: u8, panel_scale: u16) -> [[u16; 4]; 3] {
+        // Lowering the threshold by a fraction of a step in some frames turns
+        // pixels on for part of the frames, depending on the bits below the step.
+        // The pattern is shifted per pixel, so the display doesn't flicker as a whole.
+        let mut thresholds = [self.threshold(pass) as u16; 4];
+        if self.dithering {
+            for (i, threshold) in thresholds.iter_mut().enumerate() {
+                *threshold -= (i as u16 * self.brightness_step as u16) >> 2;
+            }
+        }
+        // Scale the thresholds instead of every value for the global brightness,
+        // white balance and panel calibration
+        let scaled = |channel: usize| {
+            let mut thresholds = thresholds;
+            for threshold in thresholds.iter_mut() {
+                *threshold = match self.channel_scale(channel) * panel_scale / 255 {
+                    0 => u16::MAX,
+                    scale => (*threshold * 255).div_ceil(scale),
+                };
+            }
+            thresholds
+        };
+        [scaled(0), scaled(1), scaled(2)]
+    }
+
+    /// Pixels of `frame` shifted out at `column`, after the column map and
+    /// output filter
+    fn pixels<F: Frame<NUM_ROWS, ROW_LENGTH>>(
+        &self,
+        frame: &F,
+        row: usize,
+        column: usize,
+    ) -> (u8, u8, u8, u8, u8, u8) {
+        let column = match self.column_map.and_then(|map| map.get(column)) {
+            Some(&source) if source < ROW_LENGTH => source,
+            _ => column,
+        };
+        let element = frame.pixels(row, column);
+        let filter = match self.filter {
+            Some(filter) => filter,
+            None => return element,
+        };
+        let upper = filter.apply(column, row, Rgb888::new(element.0, element.1, element.2));
+        let lower = filter.apply(
+            column,
+            row + NUM_ROWS,
+            Rgb888::new(element.3, element.4, element.5),
+        );
+        (
+            upper.r(),
+            upper.g(),
+            upper.b(),
+            lower.r(),
+            lower.g(),
+            lower.b(),
+        )
+    }
+
+    /// Drive the address pins
+    fn select_row(&mut self, count: usize) -> Result<(), PINS::Error> {
+        fn set<P: OutputPin>(pin: &mut P, high: bool) -> Result<(), P::Error> {
+            if high {
+                pin.set_high()
+            } else {
+                pin.set_low()
+            }
+        }
+        let pins = &mut self.pins;
+        let mut result = Ok(());
+        self.addressing
+            .select(count, NUM_ROWS, &mut |signal, high| {
+                if result.is_err() {
+                    return;
+                }
+                result = match signal {
+                    Signal::A => set(pins.a(), high),
+                    Signal::B => set(pins.b(), high),
+                    Signal::C => set(pins.c(), high),
+                    Signal::D => set(pins.d(), high),
+                    Signal::E => match pins.e() {
+                        Some(e) => set(e, high),
+                        None => Ok(()),
+                    },
+                };
+            });
+        result
+    }
+
+    fn render_bitplanes<F: Frame<NUM_ROWS, ROW_LENGTH>>(&self, frame: &F, out: &mut [u16]) {
+        let mut rows = out.chunks_exact_mut(ROW_LENGTH);
+        for plane in 0..self.brightness_bits() {
+            for (row, out) in (0..NUM_ROWS).zip(&mut rows) {
+                self.render_row(frame, row, plane, out);
+            }
+        }
+    }
+
+    fn render_row<F: Frame<NUM_ROWS, ROW_LENGTH>>(
+        &self,
+        frame: &F,
+        row: usize,
+        plane: u8,
+        out: &mut [u16],
+    ) {
+        let bit = 1 << (plane + 8 - self.brightness_bits());
+        let correction = self.color_correction;
+        let channels = self.pin_channels();
+        for (column, word) in out[..ROW_LENGTH].iter_mut().enumerate() {
+            let panel_scale = self.panel_scale(column);
+            let dim = |value: u8, channel| {
+                (value as u16 * (self.channel_scale(channel) * panel_scale / 255) / 255) as u8
+            };
+            let element = self.pixels(frame, row, column);
+            let values = [
+                element.0, element.1, element.2, element.3, element.4, element.5,
+            ];
+            *word = 0;
+            for (pin, &channel) in channels.iter().enumerate() {
+                let value = correction[channel][values[pin / 3 * 3 + channel] as usize];
+                *word |= ((dim(value, channel) & bit != 0) as u16) << pin;
+            }
+        }
+    }
+
+    /// Brightness a pixel needs to be lit during the pwm cycle `pass`
+    fn threshold(&self, pass: u8) -> u8 {
+        let mut index = pass + 1;
+        if self.interleaved {
+            // Reversing the bits maps 1..2^bits onto itself, spreading every
+            // brightness level evenly over the frame
+            let bits = self.brightness_bits();
+            index = index.reverse_bits() >> (8 - bits);
+        }
+        index.saturating_mul(self.brightness_step)
+    }
+
+    /// The color channel driven by each of r1, g1, b1, r2, g2, b2
+    fn pin_channels(&self) -> [usize; 6] {
+        let [upper, lower] = [
+            self.color_order[0].channels(),
+            self.color_order[1].channels(),
+        ];
+        [upper[0], upper[1], upper[2], lower[0], lower[1], lower[2]]
+    }
+
+    /// Combined global brightness and white balance of a channel, 255 is full
+    fn channel_scale(&self, channel: usize) -> u16 {
+        let brightness = self.global_brightness.min(self.limited_brightness);
+        brightness as u16 * self.white_balance[channel] as u16 / 255
+    }
+
+    /// Brightness of the panel showing `column`, see `set_panel_calibration`
+    fn panel_scale(&self, column: usize) -> u16 {
+        self.calibration
+            .and_then(|calibration| {
+                let panel = column / calibration.width.max(1);
+                calibration.scales.get(panel).copied()
+            })
+            .unwrap_or(255) as u16
+    }
+
+    /// Estimate the current of `frame` and limit the brightness accordingly
+    fn limit_current<F: Frame<NUM_ROWS, ROW_LENGTH>>(&mut self, frame: &F) {
+        if self.current_limit.is_none() {
+            self.limited_brightness = 255;
+            return;
+        }
+        // Only one row of each half is lit at a time, so the brightest pair of
+        // rows draws the peak current
+        let peak = (0..NUM_ROWS)
+            .map(|row| self.row_current(frame, row))
+            .max()
+            .unwrap_or(0);
+        self.apply_current_limit(peak);
+    }
+
+    /// Current of a pair of rows at full brightness, in units of
+    /// 1 / (255 * 255) of a fully lit led
+    fn row_current<F: Frame<NUM_ROWS, ROW_LENGTH>>(&self, frame: &F, row: usize) -> u32 {
+        let [r, g, b] = self.color_correction;
+        let [wr, wg, wb] = self.white_balance.map(|scale| scale as u32);
+        let mut sum = 0;
+        for column in 0..ROW_LENGTH {
+            let element = self.pixels(frame, row, column);
+            sum += (r[element.0 as usize] as u32 + r[element.3 as usize] as u32) * wr
+                + (g[element.1 as usize] as u32 + g[element.4 as usize] as u32) * wg
+                + (b[element.2 as usize] as u32 + b[element.5 as usize] as u32) * wb;
+        }
+        sum
+    }
+
+    /// Limit the brightness for the peak row current of a frame
+    fn apply_current_limit(&mut self, peak: u32) {
+        let limit = match self.current_limit {
+            Some(limit) => limit,
+            None => {
+                self.limited_brightness = 255;
+                return;
+            }
+        };
+        self.estimated_current = (peak as u64 * limit.led_ma as u64 / (255 * 255)) as u32;
+        self.limited_brightness = if self.estimated_current > limit.budget_ma {
+            (limit.budget_ma as u64 * 255 / self.estimated_current as u64) as u8
+        } else {
+            255
+        };
+    }
+
+    fn step_cost(&self) -> StepCost {
+        // Six data pins and two clock edges per pixel, two edges each of oe and
+        // lat, and the address pins of the row needing the most writes
+        let address_writes = (0..NUM_ROWS)
+            .map(|row| {
+                let mut writes = 0;
+                self.addressing
+                    .select(row, NUM_ROWS, &mut |_, _| writes += 1);
+                writes
+            })
+            .max()
+            .unwrap_or(0);
+        // The current is estimated from the same pixels again
+        let pixels = if self.current_limit.is_some() {
+            2 * ROW_LENGTH
+        } else {
+            ROW_LENGTH
+        };
+        StepCost {
+            pin_writes: ROW_LENGTH * 8 + 4 + address_writes,
+            pixels,
+            delay_us: 3 * self.latch_blanking as u32,
+        }
+    }
+
+    fn brightness_bits(&self) -> u8 {
+        8 - self.brightness_step.trailing_zeros() as u8
+    }
+
+    fn set_brightness_bits(&mut self, brightness_bits: u8) -> Result<(), ConfigError> {
+        if brightness_bits == 0 || brightness_bits > 8 {
+            return Err(ConfigError::InvalidBrightnessBits);
+        }
+        self.brightness_step = 1 << (8 - brightness_bits);
+        self.brightness_count = ((1 << brightness_bits as u16) - 1) as u8;
+        self.step_row = 0;
+        self.step_brightness = 0;
+        Ok(())
+    }
 }
 
 use embedded_graphics::{
-    drawable::{Dimensions, Pixel},
-    pixelcolor::Rgb565,
-    Drawing, SizedDrawing,
+    pixelcolor::{raw::RawU16, Rgb565, Rgb888},
+    prelude::*,
+    primitives::Rectangle,
 };
-impl<PINS: Outputs> Drawing<Rgb565> for Hub75<PINS> {
-    fn draw<T>(&mut self, item_pixels: T)
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA> DrawTarget
+    for Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>
+where
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+    COLOR: PixelColor + Into<Rgb888>,
+{
+    type Color = COLOR;
+    /// Only returned with `set_strict_bounds`
+    type Error = OutOfBounds;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
     where
-        T: IntoIterator<Item = Pixel<Rgb565>>,
+        I: IntoIterator<Item = Pixel<Self::Color>>,
     {
-        // This table remaps linear input values
-        // (the numbers we’d like to use; e.g. 127 = half brightness)
-        // to nonlinear gamma-corrected output values
-        // (numbers producing the desired effect on the LED;
-        // e.g. 36 = half brightness).
-        const GAMMA8: [u8; 256] = [
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
-            1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4,
-            4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11,
-            12, 12, 13, 13, 13, 14, 14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22,
-            22, 23, 24, 24, 25, 25, 26, 27, 27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37,
-            38, 39, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58,
-            59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72, 73, 74, 75, 77, 78, 79, 81, 82, 83, 85,
-            86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104, 105, 107, 109, 110, 112, 114,
-            115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137, 138, 140, 142, 144,
-            146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175, 177, 180,
-            182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
-            223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
-        ];
-        for Pixel(coord, color) in item_pixels {
-            let row = coord[1] % NUM_ROWS as u32;
-            let data = &mut self.data[row as usize][coord[0] as usize];
-            if coord[1] >= NUM_ROWS as u32 {
-                data.3 = GAMMA8[color.r() as usize];
-                data.4 = GAMMA8[color.g() as usize];
-                data.5 = GAMMA8[color.b() as usize];
-            } else {
-                data.0 = GAMMA8[color.r() as usize];
-                data.1 = GAMMA8[color.g() as usize];
-                data.2 = GAMMA8[color.b() as usize];
+        for Pixel(coord, color) in pixels {
+            match self.map_point(coord.x, coord.y) {
+                Some((x, y, segment)) => self.set_pixel(x, y, segment, color.into()),
+                None if self.strict_bounds => return Err(OutOfBounds { point: coord }),
+                None => {}
             }
         }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        // Convert the color only once and skip everything outside
+        let color: Rgb888 = color.into();
+        let bounds = self.bounding_box();
+        if self.strict_bounds {
+            if let Some(point) = area.points().find(|point| !bounds.contains(*point)) {
+                return Err(OutOfBounds { point });
+            }
+        }
+        let area = area.intersection(&bounds);
+        if self.layout.is_straight() {
+            self.fill_straight(&area, color);
+            return Ok(());
+        }
+        for point in area.points() {
+            if let Some((x, y, segment)) = self.map_point(point.x, point.y) {
+                self.set_pixel(x, y, segment, color);
+            }
+        }
+        Ok(())
     }
 }
 
-// TODO Does it make sense to include this?
-impl<PINS: Outputs> SizedDrawing<Rgb565> for Hub75<PINS> {
-    fn draw_sized<T>(&mut self, item_pixels: T)
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA>
+    Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>
+where
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+{
+    /// Fill a rectangle, like `fill_solid`
+    ///
+    /// On straight panels without rotation or mirroring, the rows of the
+    /// buffer are filled directly, which is a lot faster than drawing pixel by
+    /// pixel, e.g. for bar graphs.
+    pub fn fill_rect(&mut self, area: &Rectangle, color: COLOR) -> Result<(), OutOfBounds>
     where
-        T: IntoIterator<Item = Pixel<Rgb565>> + Dimensions,
+        COLOR: PixelColor + Into<Rgb888>,
     {
-        self.draw(item_pixels);
+        self.fill_solid(area, color)
+    }
+
+    /// Draw a horizontal line of `length` pixels, starting at `start` to the right
+    pub fn draw_hline(&mut self, start: Point, length: u32, color: COLOR) -> Result<(), OutOfBounds>
+    where
+        COLOR: PixelColor + Into<Rgb888>,
+    {
+        self.fill_solid(&Rectangle::new(start, Size::new(length, 1)), color)
+    }
+
+    /// Draw a vertical line of `length` pixels, starting at `start` downwards
+    pub fn draw_vline(&mut self, start: Point, length: u32, color: COLOR) -> Result<(), OutOfBounds>
+    where
+        COLOR: PixelColor + Into<Rgb888>,
+    {
+        self.fill_solid(&Rectangle::new(start, Size::new(1, length)), color)
+    }
+
+    /// Copy a raw image, e.g. from flash, to `(x, y)`
+    ///
+    /// `data` contains `width * height` pixels, row by row. Parts outside of the
+    /// drawing area are skipped, like pixels missing at the end of a short
+    /// `data`. A lot faster than drawing an `ImageRaw`.
+    pub fn blit(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        format: PixelFormat,
+    ) {
+        let bytes = match format {
+            PixelFormat::Rgb888 => 3,
+            PixelFormat::Rgb565 => 2,
+        };
+        let pixels = data.chunks_exact(bytes);
+        for (i, pixel) in pixels.take(width as usize * height as usize).enumerate() {
+            let (column, row) = ((i % width as usize) as i32, (i / width as usize) as i32);
+            let (x, y, segment) = match self.map_point(x + column, y + row) {
+                Some(point) => point,
+                None => continue,
+            };
+            let color = match format {
+                PixelFormat::Rgb888 => Rgb888::new(pixel[0], pixel[1], pixel[2]),
+                PixelFormat::Rgb565 => {
+                    let raw = RawU16::new(u16::from_be_bytes([pixel[0], pixel[1]]));
+                    Rgb565::from(raw).into()
+                }
+            };
+            self.set_pixel(x, y, segment, color);
+        }
+    }
+
+    /// Draw `image` with its top left corner at `position`, e.g. a `tinybmp`
+    /// image
+    ///
+    /// Unlike drawing the image directly, the pixels are written with their
+    /// full color depth, independent of `COLOR`. They are clipped to the
+    /// drawing area.
+    pub fn draw_image<I>(&mut self, image: &I, position: Point)
+    where
+        I: ImageDrawable<Color = Rgb888>,
+    {
+        let mut target = FullColor {
+            display: self,
+            offset: position,
+        };
+        image.draw(&mut target).unwrap_or_else(|e| match e {});
+    }
+
+    /// Write a pixel at a position in the draw buffer
+    fn set_pixel(&mut self, x: usize, y: usize, segment: usize, color: Rgb888) {
+        let layout = self.layout;
+        layout.set_pixel(self.draw_buffer(), x, y, segment, color);
+    }
+
+    /// Fill an area of the buffer for straight panels, see `Layout::is_straight`
+    fn fill_straight(&mut self, area: &Rectangle, color: Rgb888) {
+        let (values, blend) = (self.layout.channel_values(0, color), self.layout.blend);
+        let columns = area.columns();
+        let columns = columns.start as usize..columns.end as usize;
+        for y in area.rows() {
+            let (row, lower) = (y as usize % NUM_ROWS, y as usize >= NUM_ROWS);
+            for data in self.draw_buffer()[row][columns.clone()].iter_mut() {
+                write_pixel(data, lower, values, blend);
+            }
+        }
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Layout<NUM_ROWS, ROW_LENGTH> {
+    /// Size of the panels in pixels, see `Hub75::visible_size`
+    fn visible_size(&self) -> Size {
+        let (width, height) = self.multiplexing.size(ROW_LENGTH, 2 * NUM_ROWS);
+        Size::new((width * self.segments()) as u32, height as u32)
+    }
+
+    /// Map a point of the drawing area to a column and row of the display
+    ///
+    /// Returns `None` for points outside of the drawing area
+    ///
+    /// Also returns the index of the segment with `PanelColorMode::Segments`,
+    /// 0 otherwise.
+    fn map_point(&self, x: i32, y: i32) -> Option<(usize, usize, usize)> {
+        let (segment_width, height) = self.multiplexing.size(ROW_LENGTH, 2 * NUM_ROWS);
+        let (width, height) = ((segment_width * self.segments()) as i32, height as i32);
+        let (logical_width, logical_height) = self.drawing_size();
+        let (logical_width, logical_height) = (logical_width as i32, logical_height as i32);
+        if x < 0 || y < 0 || x >= logical_width || y >= logical_height {
+            return None;
+        }
+        let x = if self.mirror.0 {
+            logical_width - 1 - x
+        } else {
+            x
+        };
+        let y = if self.mirror.1 {
+            logical_height - 1 - y
+        } else {
+            y
+        };
+        let (x, y) = match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (width - 1 - y, x),
+            Rotation::Deg180 => (width - 1 - x, height - 1 - y),
+            Rotation::Deg270 => (y, height - 1 - x),
+        };
+        let (segment, x) = (x as usize / segment_width, x as usize % segment_width);
+        let (x, y) = self
+            .multiplexing
+            .map(x, y as usize, ROW_LENGTH, 2 * NUM_ROWS);
+        Some((x, y, segment))
+    }
+
+    /// Size of the drawing area, after multiplexing and rotation
+    fn drawing_size(&self) -> (usize, usize) {
+        let Size { width, height } = self.visible_size();
+        let (width, height) = (width as usize, height as usize);
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (width, height),
+            Rotation::Deg90 | Rotation::Deg270 => (height, width),
+        }
+    }
+
+    /// Number of panels side by side sharing the same pixels
+    fn segments(&self) -> usize {
+        match self.color_mode {
+            PanelColorMode::Segments => 3,
+            _ => 1,
+        }
+    }
+
+    /// Whether the drawing area matches the buffer, so rows can be accessed
+    /// directly
+    fn is_straight(&self) -> bool {
+        self.multiplexing.is_straight()
+            && self.rotation == Rotation::Deg0
+            && self.mirror == (false, false)
+            && self.color_mode != PanelColorMode::Segments
+    }
+
+    /// Write a pixel at a position in `buffer`, using the blend mode and
+    /// color mode
+    fn set_pixel(
+        &self,
+        buffer: &mut Buffer<NUM_ROWS, ROW_LENGTH>,
+        x: usize,
+        y: usize,
+        segment: usize,
+        color: Rgb888,
+    ) {
+        write_pixel(
+            &mut buffer[y % NUM_ROWS][x],
+            y >= NUM_ROWS,
+            self.channel_values(segment, color),
+            self.blend,
+        );
+    }
+
+    /// Values written to the red, green and blue channels of a pixel, `None`
+    /// for channels left unchanged
+    fn channel_values(&self, segment: usize, color: Rgb888) -> [Option<u8>; 3] {
+        // Rec. 709 weights, adding up to 256
+        let luminance =
+            ((color.r() as u16 * 54 + color.g() as u16 * 183 + color.b() as u16 * 19) >> 8) as u8;
+        match self.color_mode {
+            PanelColorMode::Color => [Some(color.r()), Some(color.g()), Some(color.b())],
+            PanelColorMode::Luminance => [Some(luminance); 3],
+            PanelColorMode::Segments => {
+                let mut values = [None; 3];
+                values[segment.min(2)] = Some(luminance);
+                values
+            }
+        }
+    }
+}
+
+/// Draws `Rgb888` pixels moved by `offset` to a display, for `draw_image`
+struct FullColor<'a, PINS, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA> {
+    display: &'a mut Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>,
+    offset: Point,
+}
+
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA> DrawTarget
+    for FullColor<'_, PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>
+where
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+{
+    type Color = Rgb888;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let point = point + self.offset;
+            if let Some((x, y, segment)) = self.display.map_point(point.x, point.y) {
+                self.display.set_pixel(x, y, segment, color);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA> Dimensions
+    for FullColor<'_, PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>
+where
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+{
+    fn bounding_box(&self) -> Rectangle {
+        let (width, height) = self.display.drawing_size();
+        Rectangle::new(-self.offset, Size::new(width as u32, height as u32))
+    }
+}
+
+/// Blend `values` into the upper or lower pixel of `data`
+fn write_pixel(
+    data: &mut (u8, u8, u8, u8, u8, u8),
+    lower: bool,
+    values: [Option<u8>; 3],
+    blend: Blend,
+) {
+    let channels = if lower {
+        [&mut data.3, &mut data.4, &mut data.5]
+    } else {
+        [&mut data.0, &mut data.1, &mut data.2]
+    };
+    for (old, new) in IntoIterator::into_iter(channels).zip(values.iter()) {
+        if let Some(new) = *new {
+            *old = match blend {
+                Blend::Replace => new,
+                Blend::Alpha(alpha) => {
+                    ((new as u16 * alpha as u16 + *old as u16 * (255 - alpha as u16)) / 255) as u8
+                }
+                Blend::Add => old.saturating_add(new),
+            };
+        }
+    }
+}
+
+impl<PINS: Outputs, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA> OriginDimensions
+    for Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>
+where
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+{
+    fn size(&self) -> Size {
+        let (width, height) = self.drawing_size();
+        Size::new(width as u32, height as u32)
     }
 }
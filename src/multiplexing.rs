@@ -0,0 +1,229 @@
+//! Pixel orders of different panel types
+//!
+//! Most indoor panels shift out every row of pixels in order, but many outdoor
+//! panels with lower scan rates (e.g. 1/8 scan on a 32 row panel) light
+//! multiple rows per half at once and spread them over the shifted out data.
+//! Panels with orders not covered here can be supported by implementing
+//! `Multiplexing`.
+
+/// Maps the pixels of a panel to their position in the buffer
+///
+/// The buffer has `columns` pixels per row and `rows` rows, counting both halves,
+/// so the upper half are the rows `0..rows / 2`.
+pub trait Multiplexing {
+    /// Size of the panel as `(width, height)` for the given buffer size
+    fn size(&self, columns: usize, rows: usize) -> (usize, usize);
+    /// Position of the pixel `(x, y)` in the buffer as `(column, row)`
+    fn map(&self, x: usize, y: usize, columns: usize, rows: usize) -> (usize, usize);
+    /// Whether `map` returns every position unchanged, which allows copying
+    /// whole rows
+    fn is_straight(&self) -> bool {
+        false
+    }
+    /// Whether the layout works with a buffer of this size
+    fn fits(&self, _columns: usize, _rows: usize) -> bool {
+        true
+    }
+}
+
+/// Every row is shifted out as is, used by most indoor panels
+pub struct Straight;
+
+impl Multiplexing for Straight {
+    fn size(&self, columns: usize, rows: usize) -> (usize, usize) {
+        (columns, rows)
+    }
+
+    fn map(&self, x: usize, y: usize, _columns: usize, _rows: usize) -> (usize, usize) {
+        (x, y)
+    }
+
+    fn is_straight(&self) -> bool {
+        true
+    }
+}
+
+/// Two rows per half light at once, with their pixels shifted out in alternating blocks
+///
+/// Used by many outdoor panels, e.g. 1/8 scan 64x32 or 1/4 scan 32x16 panels.
+/// Each row of the buffer contains `block_width` pixels of the second of the
+/// two rows, followed by `block_width` pixels of the first one and so on.
+/// With `first_row_first`, the blocks of the first row come first instead.
+/// The panel is half as wide and twice as high as the buffer, so `ROW_LENGTH`
+/// has to be twice the visible width and a multiple of `2 * block_width`.
+pub struct Stripe {
+    /// Usually the width of a single panel, some panels use 8 or 16
+    pub block_width: usize,
+    pub first_row_first: bool,
+}
+
+impl Multiplexing for Stripe {
+    fn size(&self, columns: usize, rows: usize) -> (usize, usize) {
+        (columns / 2, rows * 2)
+    }
+
+    fn fits(&self, columns: usize, _rows: usize) -> bool {
+        columns.is_multiple_of(2 * self.block_width)
+    }
+
+    fn map(&self, x: usize, y: usize, _columns: usize, rows: usize) -> (usize, usize) {
+        let (row, second_row) = paired_row(y, rows);
+        let block = x / self.block_width;
+        let column = if second_row == self.first_row_first {
+            x + (block + 1) * self.block_width
+        } else {
+            x + block * self.block_width
+        };
+        (column, row)
+    }
+}
+
+/// Buffer row of the panel row `y`, for panels lighting two rows per half at
+/// once, and whether `y` is the second of these rows
+fn paired_row(y: usize, rows: usize) -> (usize, bool) {
+    // Rows `block_rows` apart are lit at the same time
+    let block_rows = rows / 2;
+    let row = (y / (2 * block_rows)) * block_rows + y % block_rows;
+    (row, (y / block_rows) & 1 != 0)
+}
+
+/// 1/4 scan 32x16 panels, e.g. the common P10 outdoor modules
+///
+/// Use with `Hub75<_, 4, 64>`, the panels only have the a and b address pins
+/// connected, so c and d can be any unused pin.
+pub const QUARTER_SCAN_32X16: Stripe = Stripe {
+    block_width: 8,
+    first_row_first: false,
+};
+
+/// Like `Stripe`, but the blocks of the second row run from right to left
+///
+/// Each row of the buffer contains `block_width` pixels of the first row,
+/// followed by the same pixels of the second row in reverse and so on, so the
+/// data zigzags between the two rows.
+pub struct ZigZag {
+    pub block_width: usize,
+}
+
+impl Multiplexing for ZigZag {
+    fn size(&self, columns: usize, rows: usize) -> (usize, usize) {
+        (columns / 2, rows * 2)
+    }
+
+    fn fits(&self, columns: usize, _rows: usize) -> bool {
+        columns.is_multiple_of(2 * self.block_width)
+    }
+
+    fn map(&self, x: usize, y: usize, _columns: usize, rows: usize) -> (usize, usize) {
+        let (row, second_row) = paired_row(y, rows);
+        let (block, offset) = (x / self.block_width, x % self.block_width);
+        let start = 2 * block * self.block_width;
+        let column = if second_row {
+            start + 2 * self.block_width - 1 - offset
+        } else {
+            start + offset
+        };
+        (column, row)
+    }
+}
+
+/// Like `Stripe`, but the row shifted out first alternates between blocks
+///
+/// Each row of the buffer contains `block_width` pixels of the first row and
+/// `block_width` pixels of the second row, then the next `block_width` pixels
+/// of the second row followed by those of the first row and so on, so the
+/// rows form a checkerboard.
+pub struct Checkerboard {
+    pub block_width: usize,
+}
+
+impl Multiplexing for Checkerboard {
+    fn size(&self, columns: usize, rows: usize) -> (usize, usize) {
+        (columns / 2, rows * 2)
+    }
+
+    fn fits(&self, columns: usize, _rows: usize) -> bool {
+        columns.is_multiple_of(2 * self.block_width)
+    }
+
+    fn map(&self, x: usize, y: usize, _columns: usize, rows: usize) -> (usize, usize) {
+        let (row, second_row) = paired_row(y, rows);
+        let block = x / self.block_width;
+        let column = if second_row != (block & 1 != 0) {
+            x + (block + 1) * self.block_width
+        } else {
+            x + block * self.block_width
+        };
+        (column, row)
+    }
+}
+
+/// Like `Stripe`, but the blocks of the first row are shifted by half a block
+///
+/// Each row of the buffer starts with `block_width / 2` pixels of the first
+/// row, followed by `block_width` pixels of the second row, then the next
+/// `block_width` pixels of the first row and so on, ending with the last
+/// `block_width / 2` pixels of the first row. `block_width` has to be even.
+pub struct ZStripe {
+    pub block_width: usize,
+}
+
+impl Multiplexing for ZStripe {
+    fn size(&self, columns: usize, rows: usize) -> (usize, usize) {
+        (columns / 2, rows * 2)
+    }
+
+    fn fits(&self, columns: usize, _rows: usize) -> bool {
+        self.block_width.is_multiple_of(2) && columns.is_multiple_of(2 * self.block_width)
+    }
+
+    fn map(&self, x: usize, y: usize, _columns: usize, rows: usize) -> (usize, usize) {
+        let (row, second_row) = paired_row(y, rows);
+        let half = self.block_width / 2;
+        let column = if second_row {
+            x + (x / self.block_width) * self.block_width + half
+        } else {
+            x + ((x + half) / self.block_width) * self.block_width
+        };
+        (column, row)
+    }
+}
+
+/// Panels arranged in a grid, with the chain snaking between the rows
+///
+/// The chained panels share the buffer as with a single row of panels. The
+/// buffer columns are assigned to the panels row by row, starting at the top
+/// left. With `serpentine`, every other row of panels runs from right to left
+/// and is mounted upside down, which keeps the cables between the rows short.
+///
+/// Use with `Hub75<_, 16, 256>` for a 2x2 grid of 64x32 panels.
+pub struct TileLayout {
+    /// Number of panel rows
+    pub rows: usize,
+    /// Number of panels per row
+    pub columns: usize,
+    pub serpentine: bool,
+}
+
+impl Multiplexing for TileLayout {
+    fn size(&self, columns: usize, rows: usize) -> (usize, usize) {
+        let width = columns / (self.rows * self.columns);
+        (width * self.columns, rows * self.rows)
+    }
+
+    fn fits(&self, columns: usize, _rows: usize) -> bool {
+        columns.is_multiple_of(self.rows * self.columns)
+    }
+
+    fn map(&self, x: usize, y: usize, columns: usize, rows: usize) -> (usize, usize) {
+        let (width, height) = (columns / (self.rows * self.columns), rows);
+        let (tile_x, tile_y) = (x / width, y / height);
+        let (x, y) = (x % width, y % height);
+        if self.serpentine && tile_y & 1 != 0 {
+            let tile = tile_y * self.columns + self.columns - 1 - tile_x;
+            (tile * width + width - 1 - x, height - 1 - y)
+        } else {
+            ((tile_y * self.columns + tile_x) * width + x, y)
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Indexed colors, using a third of the memory of the regular buffer
+//!
+//! A pixel takes one byte instead of three, plus 768 bytes for the palette.
+//! Each pixel stores an index into a palette of 256 colors instead of the
+//! color itself. Changing a palette entry changes every pixel using it, which
+//! also allows for cheap color cycling effects.
+//!
+//! ```ignore
+//! let mut frame = PaletteBuffer::<16, 64>::new();
+//! frame.palette_mut()[1] = Rgb888::RED;
+//! Rectangle::new(Point::zero(), Size::new(8, 8))
+//!     .into_styled(PrimitiveStyle::with_fill(PaletteIndex(1)))
+//!     .draw(&mut frame)
+//!     .unwrap();
+//! display.output_frame(&frame, &mut delay).unwrap();
+//! ```
+
+use crate::Frame;
+use embedded_graphics::{
+    pixelcolor::{raw::RawU8, Rgb888},
+    prelude::*,
+};
+
+/// A color given by its position in the palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct PaletteIndex(pub u8);
+
+impl PixelColor for PaletteIndex {
+    type Raw = RawU8;
+}
+
+/// Pixel data of a whole frame, stored as palette indices
+///
+/// Laid out like `Buffer`, with the indices of the upper and lower half in
+/// `.0` and `.1`. Drawing ignores the rotation and multiplexing of the
+/// display.
+pub struct PaletteBuffer<const NUM_ROWS: usize, const ROW_LENGTH: usize> {
+    data: [[(u8, u8); ROW_LENGTH]; NUM_ROWS],
+    palette: [Rgb888; 256],
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> PaletteBuffer<NUM_ROWS, ROW_LENGTH> {
+    /// Create a new buffer, with every pixel set to index 0 and an all black palette
+    ///
+    /// Usable in a `static`, e.g. to keep the buffer out of the stack.
+    pub const fn new() -> Self {
+        Self {
+            data: [[(0, 0); ROW_LENGTH]; NUM_ROWS],
+            palette: [Rgb888::BLACK; 256],
+        }
+    }
+
+    /// Set every pixel to index 0
+    pub fn clear(&mut self) {
+        for row in self.data.iter_mut() {
+            for e in row.iter_mut() {
+                *e = (0, 0);
+            }
+        }
+    }
+
+    pub fn palette(&self) -> &[Rgb888; 256] {
+        &self.palette
+    }
+
+    pub fn palette_mut(&mut self) -> &mut [Rgb888; 256] {
+        &mut self.palette
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Default
+    for PaletteBuffer<NUM_ROWS, ROW_LENGTH>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Frame<NUM_ROWS, ROW_LENGTH>
+    for PaletteBuffer<NUM_ROWS, ROW_LENGTH>
+{
+    fn pixels(&self, row: usize, column: usize) -> (u8, u8, u8, u8, u8, u8) {
+        let (upper, lower) = self.data[row][column];
+        let upper = self.palette[upper as usize];
+        let lower = self.palette[lower as usize];
+        (
+            upper.r(),
+            upper.g(),
+            upper.b(),
+            lower.r(),
+            lower.g(),
+            lower.b(),
+        )
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> DrawTarget
+    for PaletteBuffer<NUM_ROWS, ROW_LENGTH>
+{
+    type Color = PaletteIndex;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels {
+            if coord.x < 0 || coord.y < 0 {
+                continue;
+            }
+            let (x, y) = (coord.x as usize, coord.y as usize);
+            if x >= ROW_LENGTH || y >= NUM_ROWS * 2 {
+                continue;
+            }
+            let data = &mut self.data[y % NUM_ROWS][x];
+            if y >= NUM_ROWS {
+                data.1 = color.0;
+            } else {
+                data.0 = color.0;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> OriginDimensions
+    for PaletteBuffer<NUM_ROWS, ROW_LENGTH>
+{
+    fn size(&self) -> Size {
+        Size::new(ROW_LENGTH as u32, (NUM_ROWS * 2) as u32)
+    }
+}
@@ -0,0 +1,84 @@
+//! Dimming with a hardware pwm channel on the oe pin
+//!
+//! `set_brightness` lowers the pwm thresholds, which loses color depth at low
+//! brightness. Driving oe with a pwm channel instead shortens the time every row
+//! is lit, keeping all colors.
+//!
+//! ```ignore
+//! let oe = PwmOe::new(pwm_channel).unwrap();
+//! let mut display = Hub75::new((r1, g1, b1, r2, g2, b2, a, b, c, d, clk, lat, oe), 4);
+//! display.pins_mut().12.set_brightness(64).unwrap();
+//! ```
+
+use embedded_hal::digital::{self, ErrorType, OutputPin};
+use embedded_hal::pwm::SetDutyCycle;
+
+/// An oe pin driven by a pwm channel
+///
+/// The channel has to be set up with inverted polarity, so the output is low
+/// during the duty cycle. Its frequency should be a lot higher than the row
+/// rate, otherwise rows are lit unevenly.
+///
+/// Errors of the channel are passed on as errors of the pin, so they have to
+/// be errors of the other pins as well, e.g. `Infallible` with most HALs.
+pub struct PwmOe<P> {
+    pwm: P,
+    brightness: u8,
+    /// Whether the output is enabled
+    enabled: bool,
+}
+
+impl<P: SetDutyCycle> PwmOe<P> {
+    /// Wrap a pwm channel, starting at full brightness with the output disabled
+    pub fn new(mut pwm: P) -> Result<Self, P::Error> {
+        pwm.set_duty_cycle_fully_off()?;
+        Ok(Self {
+            pwm,
+            brightness: 255,
+            enabled: false,
+        })
+    }
+
+    /// Set the fraction of the time rows are lit, 255 is always
+    pub fn set_brightness(&mut self, brightness: u8) -> Result<(), P::Error> {
+        self.brightness = brightness;
+        if self.enabled {
+            self.pwm.set_duty_cycle_fraction(brightness as u16, 255)?;
+        }
+        Ok(())
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    /// Return the pwm channel
+    pub fn release(self) -> P {
+        self.pwm
+    }
+}
+
+impl<P: SetDutyCycle> ErrorType for PwmOe<P>
+where
+    P::Error: digital::Error,
+{
+    type Error = P::Error;
+}
+
+impl<P: SetDutyCycle> OutputPin for PwmOe<P>
+where
+    P::Error: digital::Error,
+{
+    /// Enable the output, lighting the rows for the duty cycle
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.enabled = true;
+        self.pwm
+            .set_duty_cycle_fraction(self.brightness as u16, 255)
+    }
+
+    /// Disable the output
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.enabled = false;
+        self.pwm.set_duty_cycle_fully_off()
+    }
+}
@@ -0,0 +1,517 @@
+//! Passing frames between a producer and the refresh loop, e.g. between cores
+//!
+//! One side decodes frames, e.g. from an sd card or the network, into a
+//! `Producer`, while the other one shows them with a `Consumer`. The frames are
+//! written and read in place, without copying. If the producer is faster than
+//! the refresh, the oldest queued frame is dropped.
+//!
+//! ```ignore
+//! static QUEUE: FrameQueue<Framebuffer<16, 64>, 3> =
+//!     FrameQueue::new([Framebuffer::new(), Framebuffer::new(), Framebuffer::new()]);
+//! let (mut producer, mut consumer) = QUEUE.split().unwrap();
+//!
+//! // Core 0
+//! loop {
+//!     producer.push(|frame| decode_next(frame));
+//! }
+//!
+//! // Core 1
+//! loop {
+//!     consumer.pop(|frame| display.output_frame(frame, &mut delay));
+//! }
+//! ```
+//!
+//! `Hub75::split` does the same for a whole display, handing out a `Drawer`
+//! for drawing and a `Refresher` owning the pins:
+//!
+//! ```ignore
+//! static QUEUE: FrameQueue<Framebuffer<16, 64>, 2> =
+//!     FrameQueue::new([Framebuffer::new(), Framebuffer::new()]);
+//! let (mut drawer, mut refresher) = display.split(&QUEUE).ok().unwrap();
+//!
+//! // Core 0
+//! loop {
+//!     drawer.clear(Rgb565::BLACK).unwrap();
+//!     // draw to drawer
+//!     drawer.show();
+//! }
+//!
+//! // Core 1
+//! loop {
+//!     refresher.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use crate::{framebuffer::Framebuffer, Buffer, Hub75, Layout, Outputs};
+use core::borrow::BorrowMut;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+use embedded_hal::delay::DelayNs;
+
+/// States of a slot, the upper bits hold the sequence number of its frame
+const FREE: u32 = 0;
+const WRITING: u32 = 1;
+const READY: u32 = 2;
+const READING: u32 = 3;
+const STATE_MASK: u32 = 3;
+
+/// Up to `N` queued frames of type `T`, `N` being at least 2
+///
+/// Once the consumer took a frame, its slot is kept until the next one is
+/// taken, so it can be shown again while no new frame is queued. That leaves
+/// `N - 1` slots for queued frames.
+pub struct FrameQueue<T, const N: usize> {
+    frames: UnsafeCell<[T; N]>,
+    slots: [AtomicU32; N],
+    split: AtomicBool,
+}
+
+// The slots are only accessed by whoever claimed them in `slots`
+unsafe impl<T: Send, const N: usize> Sync for FrameQueue<T, N> {}
+
+impl<T, const N: usize> FrameQueue<T, N> {
+    /// Create a new, empty queue using `frames` as storage
+    pub const fn new(frames: [T; N]) -> Self {
+        assert!(N >= 2, "a frame queue needs at least 2 slots");
+        #[allow(clippy::declare_interior_mutable_const)]
+        const EMPTY: AtomicU32 = AtomicU32::new(FREE);
+        Self {
+            frames: UnsafeCell::new(frames),
+            slots: [EMPTY; N],
+            split: AtomicBool::new(false),
+        }
+    }
+
+    /// Get the producer and consumer side, only once
+    pub fn split(&self) -> Option<(Producer<'_, T, N>, Consumer<'_, T, N>)> {
+        if self.split.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+        Some((
+            Producer {
+                queue: self,
+                sequence: 0,
+            },
+            Consumer {
+                queue: self,
+                shown: None,
+            },
+        ))
+    }
+
+    /// Number of queued frames, not counting the one shown by the consumer
+    pub fn len(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.load(Ordering::Relaxed) & STATE_MASK == READY)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Index and value of the ready slot with the oldest frame
+    fn oldest(&self) -> Option<(usize, u32)> {
+        let mut oldest: Option<(usize, u32)> = None;
+        for (i, slot) in self.slots.iter().enumerate() {
+            let value = slot.load(Ordering::Acquire);
+            if value & STATE_MASK != READY {
+                continue;
+            }
+            let older = match oldest {
+                Some((_, other)) => is_older(value >> 2, other >> 2),
+                None => true,
+            };
+            if older {
+                oldest = Some((i, value));
+            }
+        }
+        oldest
+    }
+
+    /// Try to move slot `i` from `from` to `state`
+    fn claim(&self, i: usize, from: u32, state: u32) -> bool {
+        self.slots[i]
+            .compare_exchange(
+                from,
+                from & !STATE_MASK | state,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+
+    fn frame(&self, i: usize) -> *mut T {
+        // Pointing to a single slot keeps the other slots accessible
+        unsafe { (self.frames.get() as *mut T).add(i) }
+    }
+}
+
+/// Whether sequence number `a` is older than `b`
+fn is_older(a: u32, b: u32) -> bool {
+    // Sequence numbers wrap around, compare their distance instead
+    (b.wrapping_sub(a) & (u32::MAX >> 2)) < 1 << 29
+}
+
+/// The writing side of a `FrameQueue`
+pub struct Producer<'a, T, const N: usize> {
+    queue: &'a FrameQueue<T, N>,
+    /// Sequence number of the next frame
+    sequence: u32,
+}
+
+impl<T, const N: usize> Producer<'_, T, N> {
+    /// Queue a frame, written in place by `write`
+    ///
+    /// The slot still holds an old frame, so `write` has to set every pixel.
+    /// Returns whether the oldest queued frame was dropped to make room.
+    pub fn push(&mut self, write: impl FnOnce(&mut T)) -> bool {
+        let queue = self.queue;
+        let (slot, dropped) = loop {
+            let free = queue
+                .slots
+                .iter()
+                .position(|slot| slot.load(Ordering::Acquire) & STATE_MASK == FREE);
+            if let Some(i) = free {
+                // Only the producer claims free slots
+                queue.slots[i].store(WRITING, Ordering::Relaxed);
+                break (i, false);
+            }
+            // The consumer may take the oldest frame in the meantime, then
+            // another one is ready or a slot is free again
+            if let Some((i, value)) = queue.oldest() {
+                if queue.claim(i, value, WRITING) {
+                    break (i, true);
+                }
+            }
+            core::hint::spin_loop();
+        };
+        write(unsafe { &mut *queue.frame(slot) });
+        queue.slots[slot].store(self.sequence << 2 | READY, Ordering::Release);
+        self.sequence = self.sequence.wrapping_add(1) & (u32::MAX >> 2);
+        dropped
+    }
+}
+
+/// The reading side of a `FrameQueue`
+pub struct Consumer<'a, T, const N: usize> {
+    queue: &'a FrameQueue<T, N>,
+    /// Slot and sequence number of the frame shown last
+    shown: Option<(usize, u32)>,
+}
+
+impl<T, const N: usize> Consumer<'_, T, N> {
+    /// Call `read` with the oldest queued frame, removing it from the queue
+    ///
+    /// Without a queued frame, the frame of the previous call is read again,
+    /// so the display can be refreshed continuously. Returns `None` only
+    /// before the first frame was pushed.
+    pub fn pop<R>(&mut self, read: impl FnOnce(&T) -> R) -> Option<R> {
+        let queue = self.queue;
+        while let Some((i, value)) = queue.oldest() {
+            if !queue.claim(i, value, READING) {
+                continue;
+            }
+            // A frame that became ready in an already scanned slot can be
+            // older than the shown one, showing it would go back in time
+            if let Some((_, shown)) = self.shown {
+                if is_older(value >> 2, shown) {
+                    queue.slots[i].store(FREE, Ordering::Release);
+                    continue;
+                }
+            }
+            if let Some((shown, _)) = self.shown.replace((i, value >> 2)) {
+                queue.slots[shown].store(FREE, Ordering::Release);
+            }
+            break;
+        }
+        let (shown, _) = self.shown?;
+        Some(read(unsafe { &*queue.frame(shown) }))
+    }
+}
+
+impl<PINS, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA>
+    Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>
+where
+    PINS: Outputs,
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+{
+    /// Split the display into a drawing and an output side, e.g. for two cores
+    ///
+    /// The frames are passed through `queue`. The `Drawer` draws with the
+    /// multiplexing, rotation, mirroring, color mode and blend mode set at
+    /// this point. Returns the display again if the queue was split already.
+    #[allow(clippy::type_complexity, clippy::result_large_err)]
+    pub fn split<'a, const N: usize>(
+        self,
+        queue: &'a FrameQueue<Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>, N>,
+    ) -> Result<
+        (
+            Drawer<'a, NUM_ROWS, ROW_LENGTH, COLOR, N>,
+            Refresher<'a, PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA, N>,
+        ),
+        Self,
+    > {
+        match queue.split() {
+            Some((producer, consumer)) => Ok((
+                Drawer {
+                    frame: Framebuffer::new(),
+                    layout: self.layout,
+                    producer,
+                },
+                Refresher {
+                    display: self,
+                    consumer,
+                },
+            )),
+            None => Err(self),
+        }
+    }
+}
+
+// Both halves are meant to be moved to another core or an interrupt handler
+const _: () = {
+    fn assert_send<T: Send>() {}
+    #[allow(dead_code)]
+    fn halves_are_send() {
+        assert_send::<Drawer<'static, 16, 64, Rgb888, 2>>();
+        assert_send::<Refresher<'static, (), 16, 64, Rgb888, [Buffer<16, 64>; 1], 2>>();
+    }
+};
+
+/// The drawing side of a split display
+///
+/// Drawing works like drawing to the display before it was split, e.g. with
+/// its rotation and multiplexing. Pixels outside of the drawing area are
+/// dropped. The content is kept after `show`, so frames can be drawn
+/// incrementally.
+pub struct Drawer<'a, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, const N: usize> {
+    frame: Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>,
+    /// Layout of the display at the time it was split
+    layout: Layout<NUM_ROWS, ROW_LENGTH>,
+    producer: Producer<'a, Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>, N>,
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, const N: usize>
+    Drawer<'_, NUM_ROWS, ROW_LENGTH, COLOR, N>
+{
+    /// Queue the frame drawn so far for output
+    ///
+    /// Returns whether an older frame was dropped, because the output fell
+    /// behind.
+    pub fn show(&mut self) -> bool {
+        let frame = self.frame.buffer();
+        self.producer.push(|queued| *queued.buffer_mut() = *frame)
+    }
+
+    /// The frame being drawn, as it's shifted out
+    ///
+    /// Unlike drawing to the `Drawer`, drawing to the frame ignores the layout
+    /// of the display.
+    pub fn frame_mut(&mut self) -> &mut Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR> {
+        &mut self.frame
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, const N: usize> DrawTarget
+    for Drawer<'_, NUM_ROWS, ROW_LENGTH, COLOR, N>
+where
+    COLOR: PixelColor + Into<Rgb888>,
+{
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let Some((x, y, segment)) = self.layout.map_point(point.x, point.y) {
+                let buffer = self.frame.buffer_mut();
+                self.layout.set_pixel(buffer, x, y, segment, color.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, const N: usize> OriginDimensions
+    for Drawer<'_, NUM_ROWS, ROW_LENGTH, COLOR, N>
+{
+    fn size(&self) -> Size {
+        let (width, height) = self.layout.drawing_size();
+        Size::new(width as u32, height as u32)
+    }
+}
+
+/// The output side of a split display
+pub struct Refresher<
+    'a,
+    PINS,
+    const NUM_ROWS: usize,
+    const ROW_LENGTH: usize,
+    COLOR,
+    DATA,
+    const N: usize,
+> {
+    display: Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>,
+    consumer: Consumer<'a, Framebuffer<NUM_ROWS, ROW_LENGTH, COLOR>, N>,
+}
+
+impl<PINS, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA, const N: usize>
+    Refresher<'_, PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA, N>
+where
+    PINS: Outputs,
+    DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+{
+    /// Output the oldest queued frame, or the last one again if none is queued
+    ///
+    /// Until the first frame is shown, the buffer of the display is output.
+    pub fn output<DELAY: DelayNs>(&mut self, delay: &mut DELAY) -> Result<(), PINS::Error> {
+        let display = &mut self.display;
+        match self
+            .consumer
+            .pop(|frame| display.output_frame(frame, &mut *delay))
+        {
+            Some(result) => result,
+            None => display.output(delay),
+        }
+    }
+
+    /// The display, e.g. for changing the brightness
+    pub fn display_mut(&mut self) -> &mut Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA> {
+        &mut self.display
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::Rotation;
+    use core::convert::Infallible;
+    use embedded_hal::digital::{ErrorType, OutputPin};
+
+    struct NoPin;
+
+    impl ErrorType for NoPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for NoPin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn fifo_order() {
+        let queue = FrameQueue::new([0; 4]);
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        assert!(queue.split().is_none());
+        for frame in 1..=3 {
+            assert!(!producer.push(|slot| *slot = frame));
+        }
+        assert_eq!(queue.len(), 3);
+        for frame in 1..=3 {
+            assert_eq!(consumer.pop(|slot| *slot), Some(frame));
+        }
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn drops_oldest_frame() {
+        let queue = FrameQueue::new([0; 3]);
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        // Before the first pop, every slot can hold a queued frame
+        for frame in 1..=3 {
+            assert!(!producer.push(|slot| *slot = frame));
+        }
+        assert!(producer.push(|slot| *slot = 4));
+        assert_eq!(consumer.pop(|slot| *slot), Some(2));
+        // Now the shown frame keeps its slot, so 3 is dropped
+        assert!(producer.push(|slot| *slot = 5));
+        assert_eq!(consumer.pop(|slot| *slot), Some(4));
+        assert!(!producer.push(|slot| *slot = 6));
+        assert_eq!(consumer.pop(|slot| *slot), Some(5));
+        assert_eq!(consumer.pop(|slot| *slot), Some(6));
+    }
+
+    #[test]
+    fn shows_last_frame_again() {
+        let queue = FrameQueue::new([0; 2]);
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        assert_eq!(consumer.pop(|slot| *slot), None);
+        producer.push(|slot| *slot = 7);
+        assert_eq!(consumer.pop(|slot| *slot), Some(7));
+        assert_eq!(consumer.pop(|slot| *slot), Some(7));
+        producer.push(|slot| *slot = 8);
+        assert_eq!(consumer.pop(|slot| *slot), Some(8));
+        assert_eq!(consumer.pop(|slot| *slot), Some(8));
+    }
+
+    #[test]
+    fn two_threads() {
+        const FRAMES: u32 = 20_000;
+        let queue = FrameQueue::new([[0u32; 64]; 3]);
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                for frame in 1..=FRAMES {
+                    producer.push(|slot| slot.fill(frame));
+                }
+            });
+            let mut last = 0;
+            while last != FRAMES {
+                if let Some(frame) = consumer.pop(|slot| {
+                    // Every frame is written completely before it's read
+                    assert!(slot.iter().all(|&value| value == slot[0]));
+                    slot[0]
+                }) {
+                    assert!(frame >= last, "frame {} after {}", frame, last);
+                    last = frame;
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn drawer_uses_layout() {
+        let pins = (
+            NoPin, NoPin, NoPin, NoPin, NoPin, NoPin, NoPin, NoPin, NoPin, NoPin, NoPin, NoPin,
+            NoPin,
+        );
+        let mut display = Hub75::<_, 4, 64, Rgb888>::new(pins, 4);
+        display.set_stripe::<8, false>();
+        display.set_rotation(Rotation::Deg90);
+        display.set_mirroring(true, false);
+        Pixel(Point::new(5, 9), Rgb888::RED)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(15, 31), Rgb888::BLUE)
+            .draw(&mut display)
+            .unwrap();
+        let expected = *display.framebuffer_mut();
+
+        let queue = FrameQueue::new([Framebuffer::new(), Framebuffer::new()]);
+        let (mut drawer, _) = display.split(&queue).ok().unwrap();
+        assert_eq!(drawer.size(), Size::new(16, 32));
+        Pixel(Point::new(5, 9), Rgb888::RED)
+            .draw(&mut drawer)
+            .unwrap();
+        Pixel(Point::new(15, 31), Rgb888::BLUE)
+            .draw(&mut drawer)
+            .unwrap();
+        Pixel(Point::new(16, 0), Rgb888::GREEN)
+            .draw(&mut drawer)
+            .unwrap();
+        assert!(drawer.frame_mut().buffer() == &expected);
+    }
+}
@@ -0,0 +1,180 @@
+//! Decoding hub75 signals back into an image, e.g. for converters or for
+//! checking the output of other controllers
+//!
+//! The signals are sampled, e.g. with gpio reads or dma, into words with one
+//! bit per signal, in the order of the pin tuple:
+//! r1, g1, b1, r2, g2, b2, a, b, c, d, e, clk, lat and oe in bit 0 to 13.
+//! Every sample is fed to a `Receiver`, which keeps track of how long each
+//! pixel was lit. The signals have to be sampled at least twice per clock
+//! period. Oe is expected to be active low and lat active high.
+//!
+//! ```ignore
+//! let mut receiver = Receiver::<16, 64>::new();
+//! loop {
+//!     for &sample in dma.read_samples() {
+//!         receiver.sample(sample);
+//!     }
+//!     if frame_timer.expired() {
+//!         receiver.draw(&mut hdmi).unwrap();
+//!         receiver.reset();
+//!     }
+//! }
+//! ```
+
+use crate::multiplexing::{Multiplexing, Straight};
+use crate::Frame;
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+
+/// Bit positions of the signals, in the order of the pin tuple
+const R1: u8 = 0;
+const B2: u8 = 5;
+const A: u8 = 6;
+const CLK: u8 = 11;
+const LAT: u8 = 12;
+const OE: u8 = 13;
+
+/// Reconstructs the image shown by a panel from its signals
+///
+/// Pixels are laid out like `Buffer`, so multiplexed panels show the order
+/// the pixels are shifted out in.
+pub struct Receiver<const NUM_ROWS: usize, const ROW_LENGTH: usize> {
+    /// Level of every signal
+    signals: u16,
+    /// Data shifted in since the last latch, r1, g1, b1, r2, g2, b2 in bit 0 to 5
+    shifted: [u8; ROW_LENGTH],
+    column: usize,
+    latched: [u8; ROW_LENGTH],
+    /// Whether a row was latched since the output was last enabled
+    fresh: bool,
+    /// How often each color of each pixel was lit
+    lit: [[[u16; 6]; ROW_LENGTH]; NUM_ROWS],
+    /// How often each row was lit
+    row_lit: [u16; NUM_ROWS],
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Receiver<NUM_ROWS, ROW_LENGTH> {
+    /// Create a new receiver, with all signals low
+    pub fn new() -> Self {
+        Self {
+            signals: 0,
+            shifted: [0; ROW_LENGTH],
+            column: 0,
+            latched: [0; ROW_LENGTH],
+            fresh: false,
+            lit: [[[0; 6]; ROW_LENGTH]; NUM_ROWS],
+            row_lit: [0; NUM_ROWS],
+        }
+    }
+
+    /// Process the levels of all signals at one point in time
+    pub fn sample(&mut self, signals: u16) {
+        let previous = self.signals;
+        self.signals = signals;
+        let rising = |signal: u8| signals & !previous & 1 << signal != 0;
+        let falling = |signal: u8| !signals & previous & 1 << signal != 0;
+        if rising(CLK) && self.column < ROW_LENGTH {
+            let data = ((signals >> R1) & ((1 << (B2 - R1 + 1)) - 1)) as u8;
+            self.shifted[self.column] = data;
+            self.column += 1;
+        }
+        if rising(LAT) {
+            self.latched = self.shifted;
+            self.column = 0;
+            self.fresh = true;
+        }
+        // Enabling the output without latching a row first only shows the
+        // previous row shortly, while the next one is shifted in
+        if falling(OE) && core::mem::replace(&mut self.fresh, false) {
+            let row = ((signals >> A) & 0b1_1111) as usize;
+            if row < NUM_ROWS {
+                self.row_lit[row] = self.row_lit[row].saturating_add(1);
+                for (pixel, latched) in self.lit[row].iter_mut().zip(self.latched.iter()) {
+                    for (i, color) in pixel.iter_mut().enumerate() {
+                        *color = color.saturating_add((latched >> i & 1) as u16);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Level of every signal after the last sample
+    pub fn signals(&self) -> u16 {
+        self.signals
+    }
+
+    /// Forget the recorded image, e.g. after every frame
+    pub fn reset(&mut self) {
+        self.lit = [[[0; 6]; ROW_LENGTH]; NUM_ROWS];
+        self.row_lit = [0; NUM_ROWS];
+    }
+
+    /// Draw the image recorded since the last reset
+    ///
+    /// Every pixel gets the average brightness it was shown with,
+    /// after color correction.
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        self.draw_mapped(&Straight, target)
+    }
+
+    /// Draw the image like `draw`, as it's shown by a multiplexed panel
+    pub fn draw_mapped<D>(
+        &self,
+        multiplexing: &dyn Multiplexing,
+        target: &mut D,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        let (width, height) = multiplexing.size(ROW_LENGTH, NUM_ROWS * 2);
+        let pixels = (0..height).flat_map(|y| {
+            (0..width).map(move |x| {
+                let (column, row) = multiplexing.map(x, y, ROW_LENGTH, NUM_ROWS * 2);
+                Pixel(Point::new(x as i32, y as i32), self.pixel(column, row))
+            })
+        });
+        target.draw_iter(pixels)
+    }
+
+    /// Color of the pixel at `(column, row)` in the layout of `Buffer`, e.g.
+    /// for checking the output in tests
+    pub fn pixel(&self, column: usize, row: usize) -> Rgb888 {
+        let lit = self.lit[row % NUM_ROWS][column];
+        let count = self.row_lit[row % NUM_ROWS].max(1) as u32;
+        let level = |color: u16| (color as u32 * 255 / count) as u8;
+        if row >= NUM_ROWS {
+            Rgb888::new(level(lit[3]), level(lit[4]), level(lit[5]))
+        } else {
+            Rgb888::new(level(lit[0]), level(lit[1]), level(lit[2]))
+        }
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Default for Receiver<NUM_ROWS, ROW_LENGTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The recorded image, e.g. for passing it on to another display
+///
+/// The values are the brightness after color correction, so passing them on
+/// unchanged needs `set_color_correction` with `LINEAR`.
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Frame<NUM_ROWS, ROW_LENGTH>
+    for Receiver<NUM_ROWS, ROW_LENGTH>
+{
+    fn pixels(&self, row: usize, column: usize) -> (u8, u8, u8, u8, u8, u8) {
+        let upper = self.pixel(column, row);
+        let lower = self.pixel(column, row + NUM_ROWS);
+        (
+            upper.r(),
+            upper.g(),
+            upper.b(),
+            lower.r(),
+            lower.g(),
+            lower.b(),
+        )
+    }
+}
@@ -0,0 +1,125 @@
+//! Scrolling text, e.g. for tickers
+//!
+//! ```ignore
+//! let mut scroller = Scroller::new("Hello", &FONT_6X10, Rgb565::RED, Direction::Left, 1);
+//! loop {
+//!     display.clear();
+//!     scroller.draw(&mut display).unwrap();
+//!     scroller.step();
+//!     display.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use embedded_graphics::{
+    mono_font::{MonoFont, MonoTextStyle},
+    prelude::*,
+    text::{Baseline, Text},
+};
+
+/// The direction the text moves in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Text moving across a draw target
+///
+/// The text enters on one side, moves until it has completely left on the
+/// other side and then starts over. Text wider than the target works just the
+/// same.
+pub struct Scroller<'a, C> {
+    text: &'a str,
+    style: MonoTextStyle<'a, C>,
+    direction: Direction,
+    /// Pixels moved per step
+    speed: u32,
+    offset: u32,
+}
+
+impl<'a, C: PixelColor> Scroller<'a, C> {
+    /// Create a new scroller, moving `speed` pixels each step
+    pub fn new(
+        text: &'a str,
+        font: &'a MonoFont<'a>,
+        color: C,
+        direction: Direction,
+        speed: u32,
+    ) -> Self {
+        Self {
+            text,
+            style: MonoTextStyle::new(font, color),
+            direction,
+            speed,
+            offset: 0,
+        }
+    }
+
+    /// Replace the text, without restarting the movement
+    pub fn set_text(&mut self, text: &'a str) {
+        self.text = text;
+    }
+
+    /// Start over, with the text outside of the target
+    pub fn reset(&mut self) {
+        self.offset = 0;
+    }
+
+    /// Move the text
+    pub fn step(&mut self) {
+        self.offset = self.offset.wrapping_add(self.speed);
+    }
+
+    /// Draw the text at its current position
+    ///
+    /// Only the text is drawn, so the target should be cleared beforehand.
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let area = target.bounding_box();
+        let font = self.style.font;
+        let count = self.text.chars().count() as u32;
+        let text_size = Size::new(
+            count * font.character_size.width + count.saturating_sub(1) * font.character_spacing,
+            font.character_size.height,
+        );
+        // Distance the text moves until it starts over
+        let distance = match self.direction {
+            Direction::Left | Direction::Right => text_size.width + area.size.width,
+            Direction::Up | Direction::Down => text_size.height + area.size.height,
+        };
+        if distance == 0 {
+            return Ok(());
+        }
+        let offset = (self.offset % distance) as i32;
+        let position = match self.direction {
+            Direction::Left | Direction::Right => {
+                let x = if self.direction == Direction::Left {
+                    area.size.width as i32 - offset
+                } else {
+                    offset - text_size.width as i32
+                };
+                Point::new(x, 0)
+            }
+            Direction::Up | Direction::Down => {
+                let y = if self.direction == Direction::Up {
+                    area.size.height as i32 - offset
+                } else {
+                    offset - text_size.height as i32
+                };
+                Point::new(0, y)
+            }
+        };
+        Text::with_baseline(
+            self.text,
+            area.top_left + position,
+            self.style,
+            Baseline::Top,
+        )
+        .draw(target)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+//! Simulated panel for developing without hardware
+//!
+//! `Panel` hands out pins that record what the driver outputs and reconstructs
+//! the image a real panel would show. The image can be drawn to any draw target,
+//! e.g. the window of `embedded-graphics-simulator`, or checked with `pixel`.
+//!
+//! ```ignore
+//! let panel = Panel::<16, 64>::new();
+//! let mut display = Hub75::<_, 16, 64>::new(panel.pins(), 4);
+//! let mut window = SimulatorDisplay::<Rgb888>::new(Size::new(64, 32));
+//! loop {
+//!     // draw to display
+//!     display.output(&mut NoDelay).unwrap();
+//!     panel.draw(&mut window).unwrap();
+//!     panel.reset();
+//! }
+//! ```
+
+use crate::multiplexing::Multiplexing;
+use crate::receiver::Receiver;
+use core::cell::RefCell;
+use core::convert::Infallible;
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+use embedded_hal::digital::{ErrorType, OutputPin};
+
+/// The recorded state of a simulated panel
+///
+/// Pixels are laid out like `Buffer`, so multiplexed panels show the order
+/// the pixels are shifted out in.
+pub struct Panel<const NUM_ROWS: usize, const ROW_LENGTH: usize> {
+    receiver: RefCell<Receiver<NUM_ROWS, ROW_LENGTH>>,
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Panel<NUM_ROWS, ROW_LENGTH> {
+    /// Create a new panel, with all signals low
+    pub fn new() -> Self {
+        Self {
+            receiver: RefCell::new(Receiver::new()),
+        }
+    }
+
+    /// Pins for `Hub75::new`, including the e pin
+    #[allow(clippy::type_complexity)]
+    pub fn pins(
+        &self,
+    ) -> (
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+        Pin<'_, NUM_ROWS, ROW_LENGTH>,
+    ) {
+        let pin = |signal| Pin {
+            panel: self,
+            signal,
+        };
+        (
+            pin(0),
+            pin(1),
+            pin(2),
+            pin(3),
+            pin(4),
+            pin(5),
+            pin(6),
+            pin(7),
+            pin(8),
+            pin(9),
+            pin(10),
+            pin(11),
+            pin(12),
+            pin(13),
+        )
+    }
+
+    /// Forget the recorded image, e.g. after every frame
+    pub fn reset(&self) {
+        self.receiver.borrow_mut().reset();
+    }
+
+    /// Draw the image recorded since the last reset
+    ///
+    /// Every pixel gets the average brightness it was shown with,
+    /// after color correction.
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        self.receiver.borrow().draw(target)
+    }
+
+    /// Draw the image like `draw`, as it's shown by a multiplexed panel
+    pub fn draw_mapped<D>(
+        &self,
+        multiplexing: &dyn Multiplexing,
+        target: &mut D,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        self.receiver.borrow().draw_mapped(multiplexing, target)
+    }
+
+    /// Color of the pixel at `(column, row)` in the layout of `Buffer`, e.g.
+    /// for checking the output in tests
+    pub fn pixel(&self, column: usize, row: usize) -> Rgb888 {
+        self.receiver.borrow().pixel(column, row)
+    }
+
+    fn set(&self, signal: u8, high: bool) {
+        let mut receiver = self.receiver.borrow_mut();
+        let signals = if high {
+            receiver.signals() | 1 << signal
+        } else {
+            receiver.signals() & !(1 << signal)
+        };
+        receiver.sample(signals);
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Default for Panel<NUM_ROWS, ROW_LENGTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A pin of a simulated panel
+pub struct Pin<'a, const NUM_ROWS: usize, const ROW_LENGTH: usize> {
+    panel: &'a Panel<NUM_ROWS, ROW_LENGTH>,
+    signal: u8,
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> ErrorType for Pin<'_, NUM_ROWS, ROW_LENGTH> {
+    type Error = Infallible;
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> OutputPin for Pin<'_, NUM_ROWS, ROW_LENGTH> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.panel.set(self.signal, false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.panel.set(self.signal, true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multiplexing::{
+        Checkerboard, Straight, Stripe, TileLayout, ZStripe, ZigZag, QUARTER_SCAN_32X16,
+    };
+    use crate::{Hub75, Rotation, LINEAR};
+    use embedded_hal::delay::DelayNs;
+
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    /// Position `(column, row)` of the only pixel shifted out lit after
+    /// drawing red at `point`, counting the rows of both halves
+    fn lit<const NUM_ROWS: usize, const ROW_LENGTH: usize>(
+        multiplexing: &'static (dyn Multiplexing + Sync),
+        rotation: Rotation,
+        point: Point,
+    ) -> (usize, usize) {
+        let panel = Panel::<NUM_ROWS, ROW_LENGTH>::new();
+        let mut display = Hub75::<_, NUM_ROWS, ROW_LENGTH, Rgb888>::new(panel.pins(), 4);
+        display.set_multiplexing(multiplexing).unwrap();
+        display.set_rotation(rotation);
+        display.set_color_correction(&LINEAR, &LINEAR, &LINEAR);
+        Pixel(point, Rgb888::RED).draw(&mut display).unwrap();
+        display.output(&mut NoDelay).unwrap();
+
+        let mut lit = None;
+        for row in 0..2 * NUM_ROWS {
+            for column in 0..ROW_LENGTH {
+                let color = panel.pixel(column, row);
+                if color != Rgb888::BLACK {
+                    assert_eq!(color, Rgb888::RED);
+                    assert_eq!(lit, None, "more than one pixel lit");
+                    lit = Some((column, row));
+                }
+            }
+        }
+        lit.expect("no pixel lit")
+    }
+
+    #[test]
+    fn straight() {
+        assert_eq!(
+            lit::<16, 64>(&Straight, Rotation::Deg0, Point::new(3, 7)),
+            (3, 7)
+        );
+        assert_eq!(
+            lit::<16, 64>(&Straight, Rotation::Deg0, Point::new(60, 30)),
+            (60, 30)
+        );
+    }
+
+    #[test]
+    fn quarter_scan() {
+        // Address row a lights the panel rows a and a + 4 of each half. Every
+        // buffer row holds 8 pixels of row a + 4, then 8 of row a and so on.
+        let expected = [
+            (Point::new(3, 7), (3, 3)),
+            (Point::new(20, 2), (44, 2)),
+            (Point::new(31, 15), (55, 7)),
+            (Point::new(9, 12), (17, 4)),
+        ];
+        for (point, position) in expected {
+            assert_eq!(
+                lit::<4, 64>(&QUARTER_SCAN_32X16, Rotation::Deg0, point),
+                position
+            );
+        }
+    }
+
+    #[test]
+    fn stripe() {
+        static STRIPE: Stripe = Stripe {
+            block_width: 16,
+            first_row_first: false,
+        };
+        static FIRST_ROW_FIRST: Stripe = Stripe {
+            block_width: 16,
+            first_row_first: true,
+        };
+        // Point (3, 7) is in the second row lit by address 3, (20, 2) in the
+        // first row lit by address 2
+        assert_eq!(
+            lit::<4, 64>(&STRIPE, Rotation::Deg0, Point::new(3, 7)),
+            (3, 3)
+        );
+        assert_eq!(
+            lit::<4, 64>(&STRIPE, Rotation::Deg0, Point::new(20, 2)),
+            (52, 2)
+        );
+        assert_eq!(
+            lit::<4, 64>(&FIRST_ROW_FIRST, Rotation::Deg0, Point::new(3, 7)),
+            (19, 3)
+        );
+        assert_eq!(
+            lit::<4, 64>(&FIRST_ROW_FIRST, Rotation::Deg0, Point::new(20, 2)),
+            (36, 2)
+        );
+    }
+
+    #[test]
+    fn zigzag() {
+        static ZIGZAG: ZigZag = ZigZag { block_width: 8 };
+        // Blocks of 8 pixels of the first row, each followed by the same
+        // pixels of the second row in reverse
+        let expected = [
+            (Point::new(3, 2), (3, 2)),
+            (Point::new(3, 7), (12, 3)),
+            (Point::new(20, 6), (43, 2)),
+            (Point::new(31, 8), (55, 4)),
+        ];
+        for (point, position) in expected {
+            assert_eq!(lit::<4, 64>(&ZIGZAG, Rotation::Deg0, point), position);
+        }
+    }
+
+    #[test]
+    fn checkerboard() {
+        static CHECKERBOARD: Checkerboard = Checkerboard { block_width: 8 };
+        // Columns 0-15 hold pixels 0-7 of the first row, then of the second
+        // row, columns 16-31 pixels 8-15 of the second row, then of the first
+        let expected = [
+            (Point::new(3, 2), (3, 2)),
+            (Point::new(3, 7), (11, 3)),
+            (Point::new(12, 2), (28, 2)),
+            (Point::new(12, 7), (20, 3)),
+            (Point::new(31, 15), (55, 7)),
+        ];
+        for (point, position) in expected {
+            assert_eq!(lit::<4, 64>(&CHECKERBOARD, Rotation::Deg0, point), position);
+        }
+    }
+
+    #[test]
+    fn z_stripe() {
+        static Z_STRIPE: ZStripe = ZStripe { block_width: 8 };
+        // Columns 0-3 hold pixels 0-3 of the first row, 4-11 pixels 0-7 of
+        // the second row, 12-19 pixels 4-11 of the first row and so on
+        let expected = [
+            (Point::new(3, 2), (3, 2)),
+            (Point::new(5, 2), (13, 2)),
+            (Point::new(5, 7), (9, 3)),
+            (Point::new(30, 2), (62, 2)),
+            (Point::new(31, 12), (59, 4)),
+        ];
+        for (point, position) in expected {
+            assert_eq!(lit::<4, 64>(&Z_STRIPE, Rotation::Deg0, point), position);
+        }
+    }
+
+    #[test]
+    fn serpentine_tiles() {
+        static TILES: TileLayout = TileLayout {
+            rows: 2,
+            columns: 2,
+            serpentine: true,
+        };
+        // 2x2 panels of 8x8 pixels, chained top left, top right, bottom right
+        // and bottom left, with the bottom ones upside down
+        let expected = [
+            (Point::new(3, 7), (3, 7)),
+            (Point::new(12, 1), (12, 1)),
+            (Point::new(13, 14), (18, 1)),
+            (Point::new(2, 10), (29, 5)),
+        ];
+        for (point, position) in expected {
+            assert_eq!(lit::<4, 32>(&TILES, Rotation::Deg0, point), position);
+        }
+    }
+
+    #[test]
+    fn rotation() {
+        let point = Point::new(3, 7);
+        assert_eq!(lit::<8, 32>(&Straight, Rotation::Deg90, point), (24, 3));
+        assert_eq!(lit::<8, 32>(&Straight, Rotation::Deg180, point), (28, 8));
+        assert_eq!(lit::<8, 32>(&Straight, Rotation::Deg270, point), (7, 12));
+    }
+}
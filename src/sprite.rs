@@ -0,0 +1,125 @@
+//! Composing frames from images, e.g. a background with moving icons
+//!
+//! ```ignore
+//! let mut sprites = Compositor::<_, 4>::new();
+//! sprites.add(Sprite::new(&BACKGROUND, Point::zero(), 0)).ok();
+//! let ball = sprites.add(Sprite::new(&BALL, Point::new(4, 4), 1).transparent(Rgb565::BLACK)).unwrap();
+//! loop {
+//!     sprites.get_mut(ball).unwrap().position.x += 1;
+//!     sprites.compose(&mut display).unwrap();
+//!     display.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use embedded_graphics::{image::GetPixel, prelude::*};
+
+/// An image at a position
+#[derive(Debug)]
+pub struct Sprite<'a, I: GetPixel> {
+    pub image: &'a I,
+    /// Position of the top left corner
+    pub position: Point,
+    /// Sprites with higher values are drawn on top of those with lower ones
+    pub z: i8,
+    /// Pixels of this color aren't drawn
+    pub transparent: Option<I::Color>,
+    pub visible: bool,
+}
+
+impl<'a, I: GetPixel> Sprite<'a, I> {
+    /// Create a new, visible sprite without a transparent color
+    pub fn new(image: &'a I, position: Point, z: i8) -> Self {
+        Self {
+            image,
+            position,
+            z,
+            transparent: None,
+            visible: true,
+        }
+    }
+
+    /// Don't draw pixels of `color`
+    pub fn transparent(mut self, color: I::Color) -> Self {
+        self.transparent = Some(color);
+        self
+    }
+}
+
+/// Handle of a sprite added to a `Compositor`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpriteId(usize);
+
+/// Draws up to `N` sprites in order
+pub struct Compositor<'a, I: GetPixel, const N: usize> {
+    sprites: [Option<Sprite<'a, I>>; N],
+}
+
+impl<'a, I: GetPixel + OriginDimensions, const N: usize> Compositor<'a, I, N> {
+    /// Create a new compositor without sprites
+    pub fn new() -> Self {
+        Self {
+            sprites: [(); N].map(|_| None),
+        }
+    }
+
+    /// Add a sprite, returning it again if all `N` slots are used
+    pub fn add(&mut self, sprite: Sprite<'a, I>) -> Result<SpriteId, Sprite<'a, I>> {
+        match self.sprites.iter().position(Option::is_none) {
+            Some(index) => {
+                self.sprites[index] = Some(sprite);
+                Ok(SpriteId(index))
+            }
+            None => Err(sprite),
+        }
+    }
+
+    pub fn get_mut(&mut self, id: SpriteId) -> Option<&mut Sprite<'a, I>> {
+        self.sprites[id.0].as_mut()
+    }
+
+    /// Remove a sprite, returning it
+    pub fn remove(&mut self, id: SpriteId) -> Option<Sprite<'a, I>> {
+        self.sprites[id.0].take()
+    }
+
+    /// Draw all visible sprites, from the lowest to the highest `z`
+    ///
+    /// Sprites with the same `z` are drawn in the order of their slots.
+    /// Only the sprites are drawn, so without a background covering the whole
+    /// target, it should be cleared beforehand.
+    pub fn compose<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = I::Color>,
+    {
+        let mut order = [0; N];
+        for (i, index) in order.iter_mut().enumerate() {
+            *index = i;
+        }
+        let z = |index: &usize| self.sprites[*index].as_ref().map_or(0, |sprite| sprite.z);
+        order.sort_unstable_by_key(|index| (z(index), *index));
+        for sprite in order
+            .iter()
+            .filter_map(|&index| self.sprites[index].as_ref())
+        {
+            if !sprite.visible {
+                continue;
+            }
+            let area = sprite.image.bounding_box();
+            let pixels = area.points().filter_map(|point| {
+                let color = sprite.image.pixel(point)?;
+                if Some(color) == sprite.transparent {
+                    return None;
+                }
+                Some(Pixel(point + sprite.position, color))
+            });
+            target.draw_iter(pixels)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, I: GetPixel + OriginDimensions, const N: usize> Default for Compositor<'a, I, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
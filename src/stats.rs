@@ -0,0 +1,120 @@
+//! Measuring the refresh rate, e.g. for choosing the brightness bits
+//!
+//! ```ignore
+//! let mut stats = Stats::new(timer);
+//! loop {
+//!     stats.output(&mut display, &mut delay).unwrap();
+//!     if stats.frames() % 1000 == 0 {
+//!         defmt::info!("{} fps, {} µs per frame", stats.fps(), stats.output_us());
+//!     }
+//! }
+//! ```
+
+use crate::{Buffer, Hub75, Outputs};
+use core::borrow::BorrowMut;
+use embedded_hal::delay::DelayNs;
+
+/// A free running microsecond counter, allowed to wrap around
+pub trait Monotonic {
+    fn now_us(&mut self) -> u32;
+}
+
+/// Timing of `output` calls
+pub struct Stats<CLOCK> {
+    clock: CLOCK,
+    /// Duration of the last output
+    output_us: u32,
+    /// Time spent in delays during the last output
+    delay_us: u32,
+    frames: u32,
+    fps: u32,
+    /// Start and number of frames of the current second
+    window_start: Option<u32>,
+    window_frames: u32,
+}
+
+impl<CLOCK: Monotonic> Stats<CLOCK> {
+    pub fn new(clock: CLOCK) -> Self {
+        Self {
+            clock,
+            output_us: 0,
+            delay_us: 0,
+            frames: 0,
+            fps: 0,
+            window_start: None,
+            window_frames: 0,
+        }
+    }
+
+    /// Output the display and record the timing
+    pub fn output<PINS, DELAY, const NUM_ROWS: usize, const ROW_LENGTH: usize, COLOR, DATA>(
+        &mut self,
+        display: &mut Hub75<PINS, NUM_ROWS, ROW_LENGTH, COLOR, DATA>,
+        delay: &mut DELAY,
+    ) -> Result<(), PINS::Error>
+    where
+        PINS: Outputs,
+        DELAY: DelayNs,
+        DATA: BorrowMut<[Buffer<NUM_ROWS, ROW_LENGTH>]>,
+    {
+        let start = self.clock.now_us();
+        let mut delay = CountingDelay { delay, ns: 0 };
+        let result = display.output(&mut delay);
+        let end = self.clock.now_us();
+        self.output_us = end.wrapping_sub(start);
+        self.delay_us = (delay.ns / 1000) as u32;
+        self.frames = self.frames.wrapping_add(1);
+        let window_start = *self.window_start.get_or_insert(start);
+        self.window_frames += 1;
+        let elapsed = end.wrapping_sub(window_start);
+        if elapsed >= 1_000_000 {
+            self.fps = (self.window_frames as u64 * 1_000_000 / elapsed as u64) as u32;
+            self.window_start = Some(end);
+            self.window_frames = 0;
+        }
+        result
+    }
+
+    /// Duration of the last output in µs
+    pub fn output_us(&self) -> u32 {
+        self.output_us
+    }
+
+    /// Time spent in delays during the last output in µs
+    pub fn delay_us(&self) -> u32 {
+        self.delay_us
+    }
+
+    /// Number of outputs so far
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Outputs per second, updated every second
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    /// Return the clock
+    pub fn release(self) -> CLOCK {
+        self.clock
+    }
+}
+
+/// Adds up the requested delays
+struct CountingDelay<'a, DELAY> {
+    delay: &'a mut DELAY,
+    ns: u64,
+}
+
+impl<DELAY: DelayNs> DelayNs for CountingDelay<'_, DELAY> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.ns += ns as u64;
+        self.delay.delay_ns(ns);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.ns += us as u64 * 1000;
+        self.delay.delay_us(us);
+    }
+}
@@ -0,0 +1,113 @@
+//! Receiving frames from a pc, e.g. over a uart or usb serial port
+//!
+//! Every frame starts with the bytes `SYNC`, followed by the red, green and
+//! blue value of every pixel of the buffer, row by row.
+//! After corrupted data, the receiver waits for the next `SYNC` and starts over.
+//!
+//! ```ignore
+//! let mut receiver = Receiver::new();
+//! loop {
+//!     match receiver.read(&mut serial, display.framebuffer_mut()) {
+//!         Ok(true) => display.swap_buffers(),
+//!         Ok(false) | Err(nb::Error::WouldBlock) => {}
+//!         Err(nb::Error::Other(e)) => panic!("{:?}", e),
+//!     }
+//!     display.output(&mut delay).unwrap();
+//! }
+//! ```
+
+use crate::Buffer;
+use embedded_hal_nb::serial;
+
+/// Marks the start of every frame
+pub const SYNC: [u8; 4] = *b"H75F";
+
+/// Writes received frames into a buffer
+///
+/// The frames contain the pixels in the order of the buffer, so they aren't
+/// rotated or mirrored, and multiplexed panels need them in the order they are
+/// shifted out in.
+pub struct Receiver<const NUM_ROWS: usize, const ROW_LENGTH: usize> {
+    /// Number of `SYNC` bytes received so far
+    synced: usize,
+    /// Number of pixel bytes of the current frame received so far
+    position: usize,
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Receiver<NUM_ROWS, ROW_LENGTH> {
+    /// Size of a frame in bytes, without `SYNC`
+    pub const FRAME_LEN: usize = NUM_ROWS * 2 * ROW_LENGTH * 3;
+
+    /// Create a new receiver, waiting for `SYNC`
+    pub fn new() -> Self {
+        Self {
+            synced: 0,
+            position: 0,
+        }
+    }
+
+    /// Wait for the next `SYNC`, dropping the rest of the current frame
+    pub fn reset(&mut self) {
+        self.synced = 0;
+        self.position = 0;
+    }
+
+    /// Handle a received byte
+    ///
+    /// Returns `true` when it was the last byte of a frame
+    pub fn feed(&mut self, byte: u8, buffer: &mut Buffer<NUM_ROWS, ROW_LENGTH>) -> bool {
+        if self.synced < SYNC.len() {
+            if byte == SYNC[self.synced] {
+                self.synced += 1;
+            } else if byte == SYNC[0] {
+                self.synced = 1;
+            } else {
+                self.synced = 0;
+            }
+            return false;
+        }
+        let pixel = self.position / 3;
+        let (x, y) = (pixel % ROW_LENGTH, pixel / ROW_LENGTH);
+        let data = &mut buffer[y % NUM_ROWS][x];
+        let channel = self.position % 3 + if y >= NUM_ROWS { 3 } else { 0 };
+        match channel {
+            0 => data.0 = byte,
+            1 => data.1 = byte,
+            2 => data.2 = byte,
+            3 => data.3 = byte,
+            4 => data.4 = byte,
+            _ => data.5 = byte,
+        }
+        self.position += 1;
+        if self.position == Self::FRAME_LEN {
+            self.reset();
+            return true;
+        }
+        false
+    }
+
+    /// Read a byte from `serial` and handle it
+    ///
+    /// Returns `true` when a frame was completed. Read errors, e.g. overruns,
+    /// drop the current frame.
+    pub fn read<S: serial::Read<u8>>(
+        &mut self,
+        serial: &mut S,
+        buffer: &mut Buffer<NUM_ROWS, ROW_LENGTH>,
+    ) -> nb::Result<bool, S::Error> {
+        match serial.read() {
+            Ok(byte) => Ok(self.feed(byte, buffer)),
+            Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
+            Err(nb::Error::Other(e)) => {
+                self.reset();
+                Err(nb::Error::Other(e))
+            }
+        }
+    }
+}
+
+impl<const NUM_ROWS: usize, const ROW_LENGTH: usize> Default for Receiver<NUM_ROWS, ROW_LENGTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,289 @@
+//! Recording the signals of the display, e.g. for comparing waveforms in tests
+//!
+//! `Trace` hands out pins and a delay that record every change of the signals
+//! with a timestamp, without waiting. The recording can be written as a VCD
+//! file, which can be opened in GTKWave or PulseView, or compared with a known
+//! good recording.
+//!
+//! ```ignore
+//! let trace = Trace::new();
+//! let mut display = Hub75::<_, 16, 64>::new(trace.pins(), 4);
+//! display.output(&mut trace.delay()).unwrap();
+//! trace.write_vcd(&mut File::create("output.vcd")?)?;
+//! ```
+
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{ErrorType, OutputPin};
+use std::io::{self, Write};
+use std::vec::Vec;
+
+/// Names of the signals, in the order of the pin tuple
+const NAMES: [&str; 14] = [
+    "r1", "g1", "b1", "r2", "g2", "b2", "a", "b", "c", "d", "e", "clk", "lat", "oe",
+];
+
+/// Levels of all signals after a change, bit `n` being the `n`th pin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub time_ns: u64,
+    pub signals: u16,
+}
+
+/// Records the signals of a display
+pub struct Trace {
+    /// Time each pin write takes
+    pin_write_ns: u32,
+    time_ns: Cell<u64>,
+    signals: Cell<u16>,
+    /// Levels of the signals when the recording started
+    initial: Cell<u16>,
+    changes: RefCell<Vec<Change>>,
+}
+
+impl Trace {
+    /// Create a new trace, with all signals low and pin writes taking 10 ns
+    pub fn new() -> Self {
+        Self::with_pin_write_ns(10)
+    }
+
+    /// Create a new trace, advancing the time by `pin_write_ns` per pin write
+    pub fn with_pin_write_ns(pin_write_ns: u32) -> Self {
+        Self {
+            pin_write_ns,
+            time_ns: Cell::new(0),
+            signals: Cell::new(0),
+            initial: Cell::new(0),
+            changes: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Pins for `Hub75::new`, including the e pin
+    #[allow(clippy::type_complexity)]
+    pub fn pins(
+        &self,
+    ) -> (
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+        TracePin<'_>,
+    ) {
+        let pin = |signal| TracePin {
+            trace: self,
+            signal,
+        };
+        (
+            pin(0),
+            pin(1),
+            pin(2),
+            pin(3),
+            pin(4),
+            pin(5),
+            pin(6),
+            pin(7),
+            pin(8),
+            pin(9),
+            pin(10),
+            pin(11),
+            pin(12),
+            pin(13),
+        )
+    }
+
+    /// A delay advancing the time of the trace instead of waiting
+    pub fn delay(&self) -> TraceDelay<'_> {
+        TraceDelay { trace: self }
+    }
+
+    /// The recorded changes, in order
+    pub fn changes(&self) -> Vec<Change> {
+        self.changes.borrow().clone()
+    }
+
+    /// Forget the recorded changes and start the time at 0 again
+    ///
+    /// The signals keep their levels.
+    pub fn reset(&self) {
+        self.changes.borrow_mut().clear();
+        self.time_ns.set(0);
+        self.initial.set(self.signals.get());
+    }
+
+    /// Write the recorded changes as a VCD file
+    pub fn write_vcd<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "$timescale 1ns $end")?;
+        writeln!(out, "$scope module hub75 $end")?;
+        for (i, name) in NAMES.iter().enumerate() {
+            writeln!(out, "$var wire 1 {} {} $end", id(i), name)?;
+        }
+        writeln!(out, "$upscope $end")?;
+        writeln!(out, "$enddefinitions $end")?;
+        let changes = self.changes.borrow();
+        let mut previous = self.initial.get();
+        writeln!(out, "#0")?;
+        writeln!(out, "$dumpvars")?;
+        for i in 0..NAMES.len() {
+            writeln!(out, "{}{}", previous >> i & 1, id(i))?;
+        }
+        writeln!(out, "$end")?;
+        for change in changes.iter() {
+            writeln!(out, "#{}", change.time_ns)?;
+            for i in 0..NAMES.len() {
+                if (change.signals ^ previous) >> i & 1 != 0 {
+                    writeln!(out, "{}{}", change.signals >> i & 1, id(i))?;
+                }
+            }
+            previous = change.signals;
+        }
+        Ok(())
+    }
+
+    fn set(&self, signal: u8, high: bool) {
+        let previous = self.signals.get();
+        let signals = if high {
+            previous | 1 << signal
+        } else {
+            previous & !(1 << signal)
+        };
+        if signals != previous {
+            self.signals.set(signals);
+            self.changes.borrow_mut().push(Change {
+                time_ns: self.time_ns.get(),
+                signals,
+            });
+        }
+        self.advance(self.pin_write_ns as u64);
+    }
+
+    fn advance(&self, ns: u64) {
+        self.time_ns.set(self.time_ns.get() + ns);
+    }
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// VCD identifier of signal `i`
+fn id(i: usize) -> char {
+    (b'!' + i as u8) as char
+}
+
+/// A pin of a `Trace`
+pub struct TracePin<'a> {
+    trace: &'a Trace,
+    signal: u8,
+}
+
+impl ErrorType for TracePin<'_> {
+    type Error = Infallible;
+}
+
+impl OutputPin for TracePin<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.trace.set(self.signal, false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.trace.set(self.signal, true);
+        Ok(())
+    }
+}
+
+/// The delay of a `Trace`
+pub struct TraceDelay<'a> {
+    trace: &'a Trace,
+}
+
+impl DelayNs for TraceDelay<'_> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.trace.advance(ns as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hub75;
+    use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+
+    const CLK: u16 = 1 << 11;
+    const LAT: u16 = 1 << 12;
+    const OE: u16 = 1 << 13;
+
+    /// Record one frame of a 4x4 panel with a diagonal line
+    fn record(trace: &Trace) {
+        let mut display = Hub75::<_, 2, 4, Rgb888>::new(trace.pins(), 2);
+        for i in 0..4 {
+            Pixel(Point::new(i, i), Rgb888::new(255, 128, 0))
+                .draw(&mut display)
+                .unwrap();
+        }
+        trace.reset();
+        display.output(&mut trace.delay()).unwrap();
+    }
+
+    #[test]
+    fn latch_and_oe_order() {
+        let trace = Trace::new();
+        record(&trace);
+        let mut previous = trace.initial.get();
+        let (mut clocks, mut latched) = (0, false);
+        let mut rows = Vec::new();
+        for change in trace.changes() {
+            let signals = change.signals;
+            let rising = |mask: u16| signals & !previous & mask != 0;
+            let falling = |mask: u16| !signals & previous & mask != 0;
+            if (signals ^ previous) >> 6 & 0b1_1111 != 0 {
+                assert!(signals & OE != 0, "address changed while lit");
+            }
+            if rising(CLK) {
+                clocks += 1;
+            }
+            if rising(LAT) {
+                assert!(signals & OE != 0, "latched while lit");
+                assert_eq!(clocks, 4, "latched after {} columns", clocks);
+                clocks = 0;
+                latched = true;
+            }
+            if falling(OE) {
+                assert!(latched, "enabled without a new row");
+                latched = false;
+                rows.push(signals >> 6 & 0b1_1111);
+            }
+            previous = signals;
+        }
+        // Both rows in each of the 3 pwm cycles, dark again at the end
+        assert_eq!(rows, [0, 1, 0, 1, 0, 1]);
+        assert!(previous & OE != 0);
+        // The last row stays lit while a whole row is shifted out, like the
+        // others
+        assert_eq!(clocks, 4);
+    }
+
+    #[test]
+    fn matches_known_good_vcd() {
+        let trace = Trace::new();
+        record(&trace);
+        let mut vcd = Vec::new();
+        trace.write_vcd(&mut vcd).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&vcd).unwrap(),
+            include_str!("../testdata/output.vcd")
+        );
+    }
+}
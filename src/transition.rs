@@ -0,0 +1,137 @@
+//! Transitions between two frames, e.g. for switching between pages
+//!
+//! ```ignore
+//! let mut transition = Transition::crossfade(&old_page, &new_page, 32);
+//! while transition.next_frame(display.framebuffer_mut()) {
+//!     display.swap_buffers();
+//!     for _ in 0..4 {
+//!         display.output(&mut delay).unwrap();
+//!     }
+//! }
+//! ```
+
+use crate::{Buffer, Frame};
+
+/// How the second frame replaces the first one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    /// Fade from one frame to the other
+    Crossfade,
+    /// Uncover the second frame from the left
+    Wipe,
+    /// Push the first frame out to the left, with the second one following it
+    Scroll,
+}
+
+/// Renders the frames of a transition, one at a time
+///
+/// Wipes and scrolls move along the rows of the buffer, so they ignore the
+/// rotation and multiplexing of the display.
+pub struct Transition<'a, A, B> {
+    from: &'a A,
+    to: &'a B,
+    effect: Effect,
+    steps: u16,
+    /// Number of frames rendered so far
+    step: u16,
+}
+
+impl<'a, A, B> Transition<'a, A, B> {
+    /// Transition from `from` to `to` in `steps` frames, the last one being `to`
+    pub fn new(from: &'a A, to: &'a B, effect: Effect, steps: u16) -> Self {
+        Self {
+            from,
+            to,
+            effect,
+            steps: steps.max(1),
+            step: 0,
+        }
+    }
+
+    pub fn crossfade(from: &'a A, to: &'a B, steps: u16) -> Self {
+        Self::new(from, to, Effect::Crossfade, steps)
+    }
+
+    pub fn wipe(from: &'a A, to: &'a B, steps: u16) -> Self {
+        Self::new(from, to, Effect::Wipe, steps)
+    }
+
+    pub fn scroll(from: &'a A, to: &'a B, steps: u16) -> Self {
+        Self::new(from, to, Effect::Scroll, steps)
+    }
+
+    /// Start over from the first frame
+    pub fn reset(&mut self) {
+        self.step = 0;
+    }
+
+    /// Whether all frames have been rendered
+    pub fn is_done(&self) -> bool {
+        self.step == self.steps
+    }
+
+    /// Render the next frame into `buffer`
+    ///
+    /// Returns `false` without touching `buffer` when the transition is done.
+    pub fn next_frame<const NUM_ROWS: usize, const ROW_LENGTH: usize>(
+        &mut self,
+        buffer: &mut Buffer<NUM_ROWS, ROW_LENGTH>,
+    ) -> bool
+    where
+        A: Frame<NUM_ROWS, ROW_LENGTH>,
+        B: Frame<NUM_ROWS, ROW_LENGTH>,
+    {
+        if self.is_done() {
+            return false;
+        }
+        self.step += 1;
+        let (step, steps) = (self.step as usize, self.steps as usize);
+        // Number of columns already showing `to`
+        let offset = ROW_LENGTH * step / steps;
+        for (row, data) in buffer.iter_mut().enumerate() {
+            for (column, e) in data.iter_mut().enumerate() {
+                *e = match self.effect {
+                    Effect::Crossfade => {
+                        let (a, b) = (self.from.pixels(row, column), self.to.pixels(row, column));
+                        let fade = |a, b| mix(a, b, self.step, self.steps);
+                        (
+                            fade(a.0, b.0),
+                            fade(a.1, b.1),
+                            fade(a.2, b.2),
+                            fade(a.3, b.3),
+                            fade(a.4, b.4),
+                            fade(a.5, b.5),
+                        )
+                    }
+                    Effect::Wipe if column < offset => self.to.pixels(row, column),
+                    Effect::Wipe => self.from.pixels(row, column),
+                    Effect::Scroll if column + offset < ROW_LENGTH => {
+                        self.from.pixels(row, column + offset)
+                    }
+                    Effect::Scroll => self.to.pixels(row, column + offset - ROW_LENGTH),
+                };
+            }
+        }
+        true
+    }
+}
+
+/// Mix two values, `step / steps` of the way from `a` to `b`
+///
+/// The values are gamma encoded, so mixing them directly would make the fade
+/// dip in the middle. They are mixed as squares instead, close enough to the
+/// light output.
+fn mix(a: u8, b: u8, step: u16, steps: u16) -> u8 {
+    let (a, b) = (a as u32 * a as u32, b as u32 * b as u32);
+    let (step, steps) = (step as u32, steps as u32);
+    let linear = (a * (steps - step) + b * step) / steps;
+    // Integer square root, bit by bit
+    let mut root = 0u8;
+    for bit in (0..8).rev() {
+        let candidate = root | 1 << bit;
+        if candidate as u32 * candidate as u32 <= linear {
+            root = candidate;
+        }
+    }
+    root
+}